@@ -0,0 +1,230 @@
+use crate::cli::Cli;
+use crate::pool;
+use crate::report::{get_result, print_error};
+use crate::wc::ResultWc;
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// A rules file is a list of [[rule]] tables. Every rule whose glob matches a
+// file applies to it; globs without a '/' match the file name anywhere, the
+// same as for --include and --exclude.
+#[derive(Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<Rule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    glob: String,
+    max_lines: Option<usize>,
+    max_bytes: Option<usize>,
+    max_line_length: Option<usize>,
+    max_words: Option<usize>,
+}
+
+pub struct Rules {
+    rules: Vec<(Rule, GlobMatcher)>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Violation {
+    pub name: String,
+    pub limit: &'static str,
+    pub max: usize,
+    pub actual: usize,
+    pub glob: String,
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|_| "Error at reading the rules".to_string())?;
+        Rules::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let file: RulesFile = toml::from_str(text).map_err(|err| err.message().to_string())?;
+        let mut rules = Vec::new();
+        for rule in file.rule {
+            let matcher = matcher(&rule.glob)?;
+            rules.push((rule, matcher));
+        }
+
+        Ok(Rules { rules })
+    }
+
+    pub fn check(&self, result: &ResultWc) -> Vec<Violation> {
+        let path = relative(&result.path);
+        let mut violations = Vec::new();
+
+        for (rule, matcher) in &self.rules {
+            if !matcher.is_match(path) {
+                continue;
+            }
+            let limits = [
+                ("max_lines", rule.max_lines, result.lines),
+                ("max_bytes", rule.max_bytes, result.size),
+                (
+                    "max_line_length",
+                    rule.max_line_length,
+                    result.max_line_length.unwrap_or(0),
+                ),
+                ("max_words", rule.max_words, result.words),
+            ];
+            for (limit, max, actual) in limits {
+                if let Some(max) = max.filter(|&max| actual > max) {
+                    violations.push(Violation {
+                        name: result.path.display().to_string(),
+                        limit,
+                        max,
+                        actual,
+                        glob: rule.glob.clone(),
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+impl Violation {
+    pub fn format(&self) -> String {
+        format!(
+            "{}: {} {} exceeded with {} ({})",
+            self.name, self.limit, self.max, self.actual, self.glob
+        )
+    }
+}
+
+pub fn matcher(glob: &str) -> Result<GlobMatcher, String> {
+    let pattern = if glob.contains('/') {
+        glob.trim_start_matches("./").to_string()
+    } else {
+        format!("**/{}", glob)
+    };
+
+    Ok(GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|err| err.to_string())?
+        .compile_matcher())
+}
+
+pub fn relative(path: &Path) -> &Path {
+    path.strip_prefix("./").unwrap_or(path)
+}
+
+// Counts every kind of number the rules can ask for, whatever the flags.
+// Files that cannot be read are reported and fail the check too.
+pub fn check_paths(paths: &[PathBuf], rules: &Rules, args: &Cli) -> (Vec<Violation>, usize) {
+    let mut args = args.clone();
+    args.count_all = true;
+    args.count_bytes = true;
+    args.max_line_length = true;
+
+    let mut violations = Vec::new();
+    let mut errors = 0;
+    let outcomes = pool::run(paths, args.jobs as usize, |path| {
+        get_result(path, &args, None)
+    });
+    for (path, outcome) in paths.iter().zip(outcomes) {
+        match outcome {
+            Ok(result) => violations.extend(rules.check(&result)),
+            Err(err) => {
+                print_error(&path.display().to_string(), &err, &args);
+                errors += 1;
+            }
+        }
+    }
+
+    (violations, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    const RULES: &str = r#"
+        [[rule]]
+        glob = "src/**/*.rs"
+        max_lines = 2
+        max_line_length = 10
+
+        [[rule]]
+        glob = "*.md"
+        max_words = 3
+    "#;
+
+    fn result(name: &str, lines: usize, words: usize, max_line_length: usize) -> ResultWc {
+        let mut result = ResultWc::total();
+        result.path = PathBuf::from(name);
+        result.lines = lines;
+        result.words = words;
+        result.max_line_length = Some(max_line_length);
+        result
+    }
+
+    #[test]
+    fn test_check_rules() {
+        let rules = Rules::parse(RULES).expect("invalid rules");
+
+        assert!(rules.check(&result("./src/a/b.rs", 2, 50, 10)).is_empty());
+        assert!(rules.check(&result("tests/b.rs", 9, 50, 99)).is_empty());
+        assert_eq!(
+            rules
+                .check(&result("src/main.rs", 3, 0, 11))
+                .iter()
+                .map(Violation::format)
+                .collect::<Vec<_>>(),
+            [
+                "src/main.rs: max_lines 2 exceeded with 3 (src/**/*.rs)",
+                "src/main.rs: max_line_length 10 exceeded with 11 (src/**/*.rs)"
+            ]
+        );
+        assert_eq!(
+            rules.check(&result("docs/README.md", 1, 4, 0))[0].limit,
+            "max_words"
+        );
+    }
+
+    #[test]
+    fn test_max_bytes_counts_line_ends() {
+        let rules = Rules::parse("[[rule]]\nglob = \"*.txt\"\nmax_bytes = 950\n").unwrap();
+        let args = Cli::parse_from(["wc", "unused"]);
+        let dir = std::env::temp_dir().join(format!("wc_check_bytes_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("failed to create test dir");
+        // 100 lines of 9 bytes and a newline: 900 bytes of text, 1000 in all.
+        let over = dir.join("over.txt");
+        let at = dir.join("at.txt");
+        fs::write(&over, "123456789\n".repeat(100)).expect("failed to create test file");
+        fs::write(&at, "123456789\n".repeat(95)).expect("failed to create test file");
+
+        let (violations, errors) = check_paths(&[over.clone(), at], &rules, &args);
+        fs::remove_dir_all(&dir).expect("error when removing test dir");
+
+        assert_eq!(errors, 0);
+        assert_eq!(
+            violations,
+            [Violation {
+                name: over.display().to_string(),
+                limit: "max_bytes",
+                max: 950,
+                actual: 1000,
+                glob: "*.txt".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(Rules::parse("[[rule]]\nglob = \"*.rs\"\nmax_lnes = 3\n").is_err());
+        assert!(Rules::parse("[[rule]]\nmax_lines = 3\n").is_err());
+        assert!(Rules::parse("[[rule]]\nglob = \"[\"\n").is_err());
+    }
+}
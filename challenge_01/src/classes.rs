@@ -0,0 +1,195 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_script::UnicodeScript;
+
+#[derive(Default, Debug, PartialEq, Serialize)]
+pub struct CharClasses {
+    pub upper: usize,
+    pub lower: usize,
+    pub other_letters: usize,
+    pub digits: usize,
+    pub other_numbers: usize,
+    pub punctuation: usize,
+    pub symbols: usize,
+    pub marks: usize,
+    pub spaces: usize,
+    pub tabs: usize,
+    pub line_breaks: usize,
+    pub other_whitespace: usize,
+    pub control: usize,
+    pub other: usize,
+    pub non_ascii: usize,
+    pub scripts: BTreeMap<String, usize>,
+}
+
+impl CharClasses {
+    // Lines come without their terminator, so each one adds a line break.
+    pub fn add_line(&mut self, line: &str) {
+        self.line_breaks += 1;
+        for c in line.chars() {
+            self.add_char(c);
+        }
+    }
+
+    fn add_char(&mut self, c: char) {
+        if !c.is_ascii() {
+            self.non_ascii += 1;
+        }
+        *self
+            .scripts
+            .entry(c.script().full_name().to_string())
+            .or_insert(0) += 1;
+
+        let class = match c {
+            ' ' => &mut self.spaces,
+            '\t' => &mut self.tabs,
+            '\n' | '\r' | '\u{0b}' | '\u{0c}' | '\u{85}' | '\u{2028}' | '\u{2029}' => {
+                &mut self.line_breaks
+            }
+            c if c.is_whitespace() => &mut self.other_whitespace,
+            c => match get_general_category(c) {
+                GeneralCategory::UppercaseLetter => &mut self.upper,
+                GeneralCategory::LowercaseLetter => &mut self.lower,
+                GeneralCategory::TitlecaseLetter
+                | GeneralCategory::ModifierLetter
+                | GeneralCategory::OtherLetter => &mut self.other_letters,
+                GeneralCategory::DecimalNumber => &mut self.digits,
+                GeneralCategory::LetterNumber | GeneralCategory::OtherNumber => {
+                    &mut self.other_numbers
+                }
+                GeneralCategory::ConnectorPunctuation
+                | GeneralCategory::DashPunctuation
+                | GeneralCategory::OpenPunctuation
+                | GeneralCategory::ClosePunctuation
+                | GeneralCategory::InitialPunctuation
+                | GeneralCategory::FinalPunctuation
+                | GeneralCategory::OtherPunctuation => &mut self.punctuation,
+                GeneralCategory::MathSymbol
+                | GeneralCategory::CurrencySymbol
+                | GeneralCategory::ModifierSymbol
+                | GeneralCategory::OtherSymbol => &mut self.symbols,
+                GeneralCategory::NonspacingMark
+                | GeneralCategory::SpacingMark
+                | GeneralCategory::EnclosingMark => &mut self.marks,
+                GeneralCategory::Control => &mut self.control,
+                _ => &mut self.other,
+            },
+        };
+        *class += 1;
+    }
+
+    pub fn add(&mut self, other: &CharClasses) {
+        for (total, count) in self.counts_mut().into_iter().zip(other.counts()) {
+            *total += count.1;
+        }
+        for (script, count) in &other.scripts {
+            *self.scripts.entry(script.clone()).or_insert(0) += count;
+        }
+    }
+
+    pub fn counts(&self) -> [(&'static str, usize); 15] {
+        [
+            ("upper", self.upper),
+            ("lower", self.lower),
+            ("other letters", self.other_letters),
+            ("digits", self.digits),
+            ("other numbers", self.other_numbers),
+            ("punctuation", self.punctuation),
+            ("symbols", self.symbols),
+            ("marks", self.marks),
+            ("spaces", self.spaces),
+            ("tabs", self.tabs),
+            ("line breaks", self.line_breaks),
+            ("other whitespace", self.other_whitespace),
+            ("control", self.control),
+            ("other", self.other),
+            ("non-ascii", self.non_ascii),
+        ]
+    }
+
+    fn counts_mut(&mut self) -> [&mut usize; 15] {
+        [
+            &mut self.upper,
+            &mut self.lower,
+            &mut self.other_letters,
+            &mut self.digits,
+            &mut self.other_numbers,
+            &mut self.punctuation,
+            &mut self.symbols,
+            &mut self.marks,
+            &mut self.spaces,
+            &mut self.tabs,
+            &mut self.line_breaks,
+            &mut self.other_whitespace,
+            &mut self.control,
+            &mut self.other,
+            &mut self.non_ascii,
+        ]
+    }
+
+    pub fn format(&self) -> String {
+        let classes: Vec<String> = self
+            .counts()
+            .iter()
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect();
+        let scripts: Vec<String> = self
+            .scripts
+            .iter()
+            .map(|(script, count)| format!("{} {}", script, count))
+            .collect();
+
+        format!(
+            "  {}\n  scripts: {}",
+            classes.join(", "),
+            scripts.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_line() {
+        let mut classes = CharClasses::default();
+        classes.add_line("Hé, 42\tПривет 漢字!\u{a0}\u{7}\u{301}");
+
+        assert_eq!(classes.upper, 2);
+        assert_eq!(classes.lower, 6);
+        assert_eq!(classes.other_letters, 2);
+        assert_eq!(classes.digits, 2);
+        assert_eq!(classes.punctuation, 2);
+        assert_eq!(classes.spaces, 2);
+        assert_eq!(classes.tabs, 1);
+        assert_eq!(classes.line_breaks, 1);
+        assert_eq!(classes.other_whitespace, 1);
+        assert_eq!(classes.control, 1);
+        assert_eq!(classes.marks, 1);
+        assert_eq!(classes.non_ascii, 11);
+        assert_eq!(classes.scripts["Latin"], 2);
+        assert_eq!(classes.scripts["Cyrillic"], 6);
+        assert_eq!(classes.scripts["Han"], 2);
+        assert_eq!(classes.scripts["Inherited"], 1);
+    }
+
+    #[test]
+    fn test_add_and_format() {
+        let mut total = CharClasses::default();
+        let mut first = CharClasses::default();
+        first.add_line("A");
+        let mut second = CharClasses::default();
+        second.add_line("b1");
+        total.add(&first);
+        total.add(&second);
+
+        assert_eq!(
+            total.format(),
+            "  upper 1, lower 1, other letters 0, digits 1, other numbers 0, punctuation 0, \
+             symbols 0, marks 0, spaces 0, tabs 0, line breaks 2, other whitespace 0, \
+             control 0, other 0, non-ascii 0\n  scripts: Common 1, Latin 2"
+        );
+    }
+}
@@ -1,6 +1,74 @@
-use clap::Parser;
+use crate::group::GroupBy;
+use crate::range::Range;
+use crate::split::{Separator, WordRule};
+use crate::template::Template;
+use crate::tokens::Vocab;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
 
-#[derive(Parser)]
+#[derive(Clone, ValueEnum)]
+pub enum DupsMode {
+    Exact,
+    Approx,
+}
+
+#[derive(Clone, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum NgramUnit {
+    Char,
+    Word,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum ProseFormat {
+    Auto,
+    Markdown,
+    Html,
+    Asciidoc,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum SortKey {
+    Lines,
+    Words,
+    Chars,
+    Bytes,
+    Name,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum Units {
+    Si,
+    Iec,
+}
+
+#[derive(Clone, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Plain,
+    Json,
+}
+
+#[derive(Clone, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Utf8,
+    Lossy,
+    Latin1,
+}
+
+#[derive(Clone, Subcommand)]
+pub enum Command {
+    Client {
+        socket: std::path::PathBuf,
+
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Clone, Parser)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
     #[clap(short = 'l')]
     pub count_lines: bool,
@@ -17,5 +85,144 @@ pub struct Cli {
     #[clap(short = 'm')]
     pub count_all: bool,
 
-    pub path: std::path::PathBuf,
+    #[clap(short = 'L')]
+    pub max_line_length: bool,
+
+    #[clap(long)]
+    pub raw: bool,
+
+    #[clap(long, conflicts_with = "tsv")]
+    pub csv: bool,
+
+    #[clap(long)]
+    pub tsv: bool,
+
+    #[clap(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "exact")]
+    pub dups: Option<DupsMode>,
+
+    #[clap(long, default_value_t = 10)]
+    pub dups_top: usize,
+
+    #[clap(long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub ngrams: Option<u16>,
+
+    #[clap(long, value_enum, default_value = "char")]
+    pub unit: NgramUnit,
+
+    #[clap(long, default_value_t = 10)]
+    pub ngrams_top: usize,
+
+    #[clap(long, conflicts_with_all = ["word_delim", "record_sep", "classes", "prose", "max_line_length"])]
+    pub estimate: bool,
+
+    #[clap(long, default_value_t = 64)]
+    pub samples: usize,
+
+    #[clap(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "auto")]
+    pub prose: Option<ProseFormat>,
+
+    #[clap(long)]
+    pub classes: bool,
+
+    #[clap(long, value_name = "VOCAB", value_parser = Vocab::load, conflicts_with = "estimate")]
+    pub tokens: Option<Vocab>,
+
+    #[clap(long, conflicts_with = "estimate")]
+    pub lang: bool,
+
+    #[clap(long, value_name = "WIDTH", num_args = 0..=1, require_equals = true, conflicts_with_all = ["estimate", "bytes_range"])]
+    pub per_line: Option<Option<usize>>,
+
+    #[clap(long)]
+    pub word_delim: Option<WordRule>,
+
+    #[clap(long)]
+    pub record_sep: Option<Separator>,
+
+    #[clap(long, conflicts_with_all = ["lines_range", "estimate"])]
+    pub bytes_range: Option<Range>,
+
+    #[clap(long, conflicts_with_all = ["estimate", "record_sep"])]
+    pub lines_range: Option<Range>,
+
+    #[clap(long)]
+    pub max_bytes: Option<u64>,
+
+    #[clap(long, conflicts_with = "estimate")]
+    pub rev: Option<String>,
+
+    #[clap(long, value_name = "RULES", conflicts_with = "estimate")]
+    pub check: Option<std::path::PathBuf>,
+
+    #[clap(short = 'r', long)]
+    pub recursive: bool,
+
+    #[clap(short = 'j', long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: u16,
+
+    #[clap(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    #[clap(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    #[clap(long, value_enum, default_value = "utf8")]
+    pub encoding: Encoding,
+
+    #[clap(long)]
+    pub progress: bool,
+
+    #[clap(long, value_enum)]
+    pub sort: Option<SortKey>,
+
+    #[clap(long)]
+    pub reverse: bool,
+
+    #[clap(long)]
+    pub top: Option<usize>,
+
+    #[clap(long, default_value_t = 0)]
+    pub min_lines: usize,
+
+    #[clap(long, default_value_t = 0)]
+    pub min_words: usize,
+
+    #[clap(long, default_value_t = 0)]
+    pub min_chars: usize,
+
+    #[clap(long, default_value_t = 0)]
+    pub min_bytes: usize,
+
+    #[clap(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "si")]
+    pub human: Option<Units>,
+
+    #[clap(long, conflicts_with = "human")]
+    pub thousands: bool,
+
+    #[clap(long)]
+    pub group_by: Option<GroupBy>,
+
+    #[clap(long, value_enum, default_value = "plain")]
+    pub format: OutputFormat,
+
+    #[clap(long, value_name = "TEMPLATE")]
+    pub printf: Option<Template>,
+
+    #[clap(long, value_name = "TEMPLATE")]
+    pub printf_total: Option<Template>,
+
+    #[clap(long, value_name = "TEMPLATE")]
+    pub printf_error: Option<Template>,
+
+    #[clap(long, value_name = "SOCKET", conflicts_with = "paths")]
+    pub serve: Option<std::path::PathBuf>,
+
+    #[clap(long)]
+    pub show_config: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    #[clap(required_unless_present_any = ["serve", "show_config"])]
+    pub paths: Vec<std::path::PathBuf>,
 }
@@ -0,0 +1,295 @@
+use crate::check::{matcher, relative};
+use crate::cli::{Cli, Encoding, OutputFormat};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Metric {
+    Lines,
+    Words,
+    Chars,
+    Bytes,
+    All,
+    MaxLineLength,
+}
+
+const METRIC_FLAGS: [&str; 6] = [
+    "count_lines",
+    "count_words",
+    "count_chars",
+    "count_bytes",
+    "count_all",
+    "max_line_length",
+];
+
+// The settings a config file can hold. A later file only overrides the
+// settings it gives.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics: Option<Vec<Metric>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<OutputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    include: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exclude: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<Encoding>,
+}
+
+impl Config {
+    fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.message().to_string())
+    }
+
+    fn load(path: &Path) -> Result<Option<Self>, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Config::parse(&text)
+                .map(Some)
+                .map_err(|err| format!("{}: {}", path.display(), err)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(_) => Err(format!("{}: Error at reading the config", path.display())),
+        }
+    }
+
+    fn merge(&mut self, other: Config) {
+        self.metrics = other.metrics.or(self.metrics.take());
+        self.format = other.format.or(self.format.take());
+        self.include = other.include.or(self.include.take());
+        self.exclude = other.exclude.or(self.exclude.take());
+        self.encoding = other.encoding.or(self.encoding.take());
+    }
+
+    // Anything given on the command line or in WC_OPTS wins. Giving any metric
+    // flag replaces all the metrics of the config.
+    fn apply(self, args: &mut Cli, matches: &ArgMatches) {
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let Some(metrics) = self
+            .metrics
+            .filter(|_| !METRIC_FLAGS.iter().any(|id| given(id)))
+        {
+            for metric in metrics {
+                match metric {
+                    Metric::Lines => args.count_lines = true,
+                    Metric::Words => args.count_words = true,
+                    Metric::Chars => args.count_chars = true,
+                    Metric::Bytes => args.count_bytes = true,
+                    Metric::All => args.count_all = true,
+                    Metric::MaxLineLength => args.max_line_length = true,
+                }
+            }
+        }
+        if let Some(format) = self.format.filter(|_| !given("format")) {
+            args.format = format;
+        }
+        if let Some(include) = self.include.filter(|_| !given("include")) {
+            args.include = include;
+        }
+        if let Some(exclude) = self.exclude.filter(|_| !given("exclude")) {
+            args.exclude = exclude;
+        }
+        if let Some(encoding) = self.encoding.filter(|_| !given("encoding")) {
+            args.encoding = encoding;
+        }
+    }
+
+    fn from_args(args: &Cli) -> Self {
+        let metrics = [
+            (args.count_lines, Metric::Lines),
+            (args.count_words, Metric::Words),
+            (args.count_chars, Metric::Chars),
+            (args.count_bytes, Metric::Bytes),
+            (args.count_all, Metric::All),
+            (args.max_line_length, Metric::MaxLineLength),
+        ];
+
+        Config {
+            metrics: Some(
+                metrics
+                    .into_iter()
+                    .filter_map(|(on, metric)| on.then_some(metric))
+                    .collect(),
+            ),
+            format: Some(args.format.clone()),
+            include: Some(args.include.clone()),
+            exclude: Some(args.exclude.clone()),
+            encoding: Some(args.encoding.clone()),
+        }
+    }
+}
+
+// The user config comes first, then the nearest .wc.toml found from the
+// current directory upwards.
+fn config_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(dir) = config_home {
+        files.push(dir.join("wc").join("config.toml"));
+    }
+    if let Ok(cwd) = env::current_dir() {
+        if let Some(dir) = cwd.ancestors().find(|dir| dir.join(".wc.toml").is_file()) {
+            files.push(dir.join(".wc.toml"));
+        }
+    }
+
+    files
+}
+
+// WC_OPTS is split on whitespace and read as if its options came first on
+// the command line, so single values given again are overridden and lists
+// such as --include add up. The client and the server skip all of this:
+// requests carry their own options. A subcommand takes no options before it,
+// so WC_OPTS is left out when one comes first.
+pub fn parse_args() -> Result<(Cli, Vec<PathBuf>), String> {
+    let mut argv: Vec<OsString> = env::args_os().collect();
+    let subcommand = argv
+        .get(1)
+        .and_then(|arg| arg.to_str())
+        .is_some_and(|arg| Cli::command().find_subcommand(arg).is_some());
+    if !subcommand {
+        let opts = env::var("WC_OPTS").unwrap_or_default();
+        argv.splice(1..1, opts.split_whitespace().map(OsString::from));
+    }
+    let matches = Cli::command()
+        .args_override_self(true)
+        .get_matches_from(argv);
+    let mut args = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if args.command.is_some() || args.serve.is_some() {
+        return Ok((args, Vec::new()));
+    }
+
+    let mut config = Config::default();
+    let mut loaded = Vec::new();
+    for file in config_files() {
+        if let Some(layer) = Config::load(&file)? {
+            config.merge(layer);
+            loaded.push(file);
+        }
+    }
+    config.apply(&mut args, &matches);
+
+    Ok((args, loaded))
+}
+
+pub fn show_config(args: &Cli, loaded: &[PathBuf]) -> String {
+    let mut shown: String = loaded
+        .iter()
+        .map(|file| format!("# {}\n", file.display()))
+        .collect();
+    if let Ok(opts) = env::var("WC_OPTS") {
+        shown.push_str(&format!("# WC_OPTS={}\n", opts));
+    }
+    shown.push_str(&toml::to_string(&Config::from_args(args)).unwrap_or_default());
+
+    shown
+}
+
+// Only the files found below a directory are filtered, the ones named on the
+// command line are always counted.
+pub fn filter_paths(paths: Vec<PathBuf>, args: &Cli) -> Result<Vec<PathBuf>, String> {
+    let include = args
+        .include
+        .iter()
+        .map(|glob| matcher(glob))
+        .collect::<Result<Vec<_>, _>>()?;
+    let exclude = args
+        .exclude
+        .iter()
+        .map(|glob| matcher(glob))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(paths
+        .into_iter()
+        .filter(|path| {
+            if args.paths.contains(path) {
+                return true;
+            }
+            let path = relative(path);
+            (include.is_empty() || include.iter().any(|glob| glob.is_match(path)))
+                && !exclude.iter().any(|glob| glob.is_match(path))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(argv: &[&str]) -> (Cli, ArgMatches) {
+        let matches = Cli::command().get_matches_from(argv);
+        (Cli::from_arg_matches(&matches).unwrap(), matches)
+    }
+
+    #[test]
+    fn test_merge_layers() {
+        let mut config = Config::parse("metrics = [\"lines\"]\nformat = \"json\"\n").unwrap();
+        config.merge(Config::parse("format = \"plain\"\nencoding = \"lossy\"\n").unwrap());
+
+        assert_eq!(config.metrics, Some(vec![Metric::Lines]));
+        assert!(matches!(config.format, Some(OutputFormat::Plain)));
+        assert!(matches!(config.encoding, Some(Encoding::Lossy)));
+        assert!(Config::parse("metric = [\"lines\"]\n").is_err());
+        assert!(Config::parse("metrics = [\"pages\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_command_line_wins() {
+        let config = || {
+            Config::parse(
+                "metrics = [\"lines\", \"max_line_length\"]\nformat = \"json\"\nexclude = [\"*.md\"]\n",
+            )
+            .unwrap()
+        };
+
+        let (mut args, matches) = parse(&["wc", "a.txt"]);
+        config().apply(&mut args, &matches);
+        assert!(args.count_lines && args.max_line_length && !args.count_words);
+        assert!(matches!(args.format, OutputFormat::Json));
+
+        let (mut args, matches) = parse(&["wc", "-w", "--format=plain", "a.txt"]);
+        config().apply(&mut args, &matches);
+        assert!(args.count_words && !args.count_lines && !args.max_line_length);
+        assert!(matches!(args.format, OutputFormat::Plain));
+        assert_eq!(args.exclude, ["*.md"]);
+    }
+
+    #[test]
+    fn test_filter_paths() {
+        let (args, _) = parse(&[
+            "wc",
+            "--include=*.rs",
+            "--include=docs/*",
+            "--exclude=**/generated/**",
+            "a",
+            "README.md",
+        ]);
+        let paths = [
+            "./src/main.rs",
+            "src/generated/table.rs",
+            "docs/guide.md",
+            "docs/deep/guide.md",
+            "README.md",
+        ];
+
+        assert_eq!(
+            filter_paths(paths.iter().map(PathBuf::from).collect(), &args),
+            Ok(vec![
+                PathBuf::from("./src/main.rs"),
+                PathBuf::from("docs/guide.md"),
+                PathBuf::from("README.md")
+            ])
+        );
+    }
+}
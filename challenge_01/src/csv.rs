@@ -0,0 +1,235 @@
+use crate::cli::Cli;
+use crate::progress::Progress;
+use crate::wc::{display_name, open_input};
+use serde::{Serialize, Serializer};
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Default, Serialize)]
+pub struct ColumnStats {
+    non_empty: usize,
+    #[serde(serialize_with = "serialize_len")]
+    distinct: HashSet<String>,
+    max_width: usize,
+}
+
+#[derive(Serialize)]
+pub struct CsvStats {
+    records: usize,
+    fields: usize,
+    ragged: Vec<(usize, usize)>,
+    columns: Vec<ColumnStats>,
+    #[serde(rename = "name")]
+    file_name: String,
+}
+
+fn serialize_len<S: Serializer>(set: &HashSet<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(set.len() as u64)
+}
+
+enum State {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    QuoteInQuoted,
+}
+
+pub fn get_csv_statistics(
+    path: &Path,
+    args: &Cli,
+    progress: Option<&Arc<Progress>>,
+) -> Result<CsvStats, String> {
+    let delimiter = if args.tsv { '\t' } else { ',' };
+    let reader = open_input(path, args, progress)?;
+
+    parse(reader, delimiter, display_name(path, args))
+}
+
+fn parse<R: BufRead>(
+    mut reader: R,
+    delimiter: char,
+    file_name: String,
+) -> Result<CsvStats, String> {
+    let mut stats = CsvStats {
+        records: 0,
+        fields: 0,
+        ragged: Vec::new(),
+        columns: Vec::new(),
+        file_name,
+    };
+
+    let mut state = State::FieldStart;
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut line = String::new();
+    let mut line_number = 0;
+    let mut record_start = 1;
+
+    loop {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|_| "error at reading line".to_string())?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+
+        if matches!(state, State::FieldStart) && record.is_empty() {
+            record_start = line_number;
+            if line.trim_end_matches(['\r', '\n']).is_empty() {
+                continue;
+            }
+        }
+
+        for c in line.chars() {
+            state = match (state, c) {
+                (State::Quoted, '"') => State::QuoteInQuoted,
+                (State::Quoted, c) => {
+                    field.push(c);
+                    State::Quoted
+                }
+                (State::QuoteInQuoted, '"') => {
+                    field.push('"');
+                    State::Quoted
+                }
+                (State::FieldStart, '"') => State::Quoted,
+                (_, '\r') => State::Unquoted,
+                (_, '\n') => {
+                    record.push(std::mem::take(&mut field));
+                    stats.add_record(std::mem::take(&mut record), record_start);
+                    State::FieldStart
+                }
+                (_, c) if c == delimiter => {
+                    record.push(std::mem::take(&mut field));
+                    State::FieldStart
+                }
+                (_, c) => {
+                    field.push(c);
+                    State::Unquoted
+                }
+            };
+        }
+    }
+
+    match state {
+        State::Quoted => {
+            return Err(format!(
+                "unterminated quoted field starting at line {}",
+                record_start
+            ))
+        }
+        State::FieldStart if record.is_empty() => {}
+        _ => {
+            record.push(field);
+            stats.add_record(record, record_start);
+        }
+    }
+
+    Ok(stats)
+}
+
+impl CsvStats {
+    fn add_record(&mut self, record: Vec<String>, line_number: usize) {
+        if self.records == 0 {
+            self.fields = record.len();
+        } else if record.len() != self.fields {
+            self.ragged.push((line_number, record.len()));
+        }
+        self.records += 1;
+
+        if self.columns.len() < record.len() {
+            self.columns.resize_with(record.len(), ColumnStats::default);
+        }
+        for (column, value) in self.columns.iter_mut().zip(record) {
+            if !value.is_empty() {
+                column.non_empty += 1;
+            }
+            column.max_width = column.max_width.max(value.chars().count());
+            column.distinct.insert(value);
+        }
+    }
+
+    pub fn format(&self) -> String {
+        let mut result = format!(
+            "{} records {} fields {}\n",
+            self.records, self.fields, self.file_name
+        );
+        for (line_number, fields) in &self.ragged {
+            result.push_str(&format!(
+                "ragged row at line {}: {} fields\n",
+                line_number, fields
+            ));
+        }
+        for (index, column) in self.columns.iter().enumerate() {
+            result.push_str(&format!(
+                "column {}: {} non-empty {} distinct {} max width\n",
+                index + 1,
+                column.non_empty,
+                column.distinct.len(),
+                column.max_width
+            ));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(data: &str, delimiter: char) -> Result<CsvStats, String> {
+        parse(data.as_bytes(), delimiter, "test.csv".to_string())
+    }
+
+    #[test]
+    fn test_parse_quoted_fields() {
+        let data = "name,quote\r\n\"Doe, John\",\"He said \"\"hi\"\"\nline\"\r\nAnn,\n";
+        let stats = parse_str(data, ',').expect("failed to parse csv");
+
+        assert_eq!(stats.records, 3);
+        assert_eq!(stats.fields, 2);
+        assert!(stats.ragged.is_empty());
+        assert_eq!(stats.columns[0].max_width, 9);
+        assert!(stats.columns[0].distinct.contains("Doe, John"));
+        assert!(stats.columns[1].distinct.contains("He said \"hi\"\nline"));
+        assert_eq!(stats.columns[1].non_empty, 2);
+    }
+
+    #[test]
+    fn test_parse_ragged_rows() {
+        let data = "a\tb\tc\n1\t2\n\n1\t2\t3\n4\t5\t6\t7";
+        let stats = parse_str(data, '\t').expect("failed to parse tsv");
+
+        assert_eq!(stats.records, 4);
+        assert_eq!(stats.ragged, vec![(2, 2), (5, 4)]);
+        assert_eq!(stats.columns.len(), 4);
+        assert_eq!(stats.columns[3].non_empty, 1);
+    }
+
+    #[test]
+    fn test_parse_unterminated_quote() {
+        let result = parse_str("a,b\n\"open,1\n", ',');
+
+        assert_eq!(
+            result.err(),
+            Some("unterminated quoted field starting at line 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let stats = parse_str("a,b\n1,1\n2\n", ',').expect("failed to parse csv");
+
+        assert_eq!(
+            stats.format(),
+            "3 records 2 fields test.csv\n\
+             ragged row at line 3: 1 fields\n\
+             column 1: 3 non-empty 3 distinct 1 max width\n\
+             column 2: 2 non-empty 2 distinct 1 max width\n"
+        );
+    }
+}
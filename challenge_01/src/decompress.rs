@@ -0,0 +1,181 @@
+use flate2::bufread::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use flate2::{FlushDecompress, Status};
+use std::io::{self, BufRead, Read};
+
+// How much of the input callers should hand to `detect`.
+pub const HEADER_SIZE: u64 = 8 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zlib,
+    Deflate,
+}
+
+pub fn detect(header: &[u8]) -> Compression {
+    match header {
+        [0x1f, 0x8b, ..] => Compression::Gzip,
+        // Only the 32K window zlib always writes, without a preset dictionary,
+        // and with the header checksum holding. Text such as "x^" still gets
+        // that far, so the data after the header has to inflate as well.
+        [0x78, flg, ..]
+            if flg & 0x20 == 0 && (0x7800 | u16::from(*flg)) % 31 == 0 && inflates(header) =>
+        {
+            Compression::Zlib
+        }
+        _ => Compression::None,
+    }
+}
+
+// Passes when the stream ends inside `header`, or when a full header inflates
+// without an error. A shorter header is all of the input, so a stream that
+// does not end in it is not zlib.
+fn inflates(header: &[u8]) -> bool {
+    let mut inflater = flate2::Decompress::new(true);
+    let mut output = vec![0; 32 * 1024];
+    loop {
+        let (consumed, produced) = (inflater.total_in() as usize, inflater.total_out());
+        match inflater.decompress(&header[consumed..], &mut output, FlushDecompress::None) {
+            Ok(Status::StreamEnd) => return true,
+            Ok(_) if inflater.total_in() as usize == header.len() => {
+                return header.len() as u64 >= HEADER_SIZE
+            }
+            Ok(_)
+                if inflater.total_in() as usize == consumed && inflater.total_out() == produced =>
+            {
+                return false
+            }
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+enum Decoder<R: BufRead> {
+    Plain(R),
+    Gzip(MultiGzDecoder<R>),
+    Zlib(ZlibDecoder<R>),
+    Deflate(DeflateDecoder<R>),
+}
+
+// The header read for detection is put back in front of the rest.
+type Input<R> = io::Chain<io::Cursor<Vec<u8>>, R>;
+
+pub struct Decompress<R: BufRead> {
+    decoder: Decoder<Input<R>>,
+}
+
+impl<R: BufRead> Decompress<R> {
+    // Raw DEFLATE has no header to sniff, so the caller has to ask for it.
+    // A pipe can hand over a few bytes at a time, so the header is read
+    // until HEADER_SIZE or the end of the input before it is sniffed.
+    pub fn new(mut reader: R, raw_deflate: bool) -> io::Result<Self> {
+        let mut header = Vec::new();
+        let compression = if raw_deflate {
+            Compression::Deflate
+        } else {
+            (&mut reader).take(HEADER_SIZE).read_to_end(&mut header)?;
+            detect(&header)
+        };
+        let reader = io::Cursor::new(header).chain(reader);
+
+        let decoder = match compression {
+            Compression::None => Decoder::Plain(reader),
+            Compression::Gzip => Decoder::Gzip(MultiGzDecoder::new(reader)),
+            Compression::Zlib => Decoder::Zlib(ZlibDecoder::new(reader)),
+            Compression::Deflate => Decoder::Deflate(DeflateDecoder::new(reader)),
+        };
+
+        Ok(Decompress { decoder })
+    }
+}
+
+impl<R: BufRead> Read for Decompress<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.decoder {
+            Decoder::Plain(reader) => reader.read(buf),
+            Decoder::Gzip(decoder) => decoder.read(buf),
+            Decoder::Zlib(decoder) => decoder.read(buf),
+            Decoder::Deflate(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use std::io::Write;
+
+    const TEXT: &str = "first line\nsecond line\n";
+
+    fn zlib(text: &str) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decode(data: &[u8], raw_deflate: bool) -> String {
+        let mut decoded = String::new();
+        Decompress::new(data, raw_deflate)
+            .expect("failed to create decoder")
+            .read_to_string(&mut decoded)
+            .expect("failed to decode");
+        decoded
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(&[0x1f, 0x8b, 0x08]), Compression::Gzip);
+        assert_eq!(detect(&zlib(TEXT)), Compression::Zlib);
+        assert_eq!(detect(&[0x78, 0x9c]), Compression::None);
+        assert_eq!(detect(b"xy"), Compression::None);
+        assert_eq!(detect(b"plain text"), Compression::None);
+        assert_eq!(detect(&[]), Compression::None);
+    }
+
+    #[test]
+    fn test_plain_passthrough() {
+        assert_eq!(decode(TEXT.as_bytes(), false), TEXT);
+        // 0x785e passes the zlib header checksum.
+        assert_eq!(detect(b"x^2 is nice\nmore text\n"), Compression::None);
+        assert_eq!(
+            decode(b"x^2 is nice\nmore text\n", false),
+            "x^2 is nice\nmore text\n"
+        );
+    }
+
+    #[test]
+    fn test_detect_across_short_reads() {
+        let data = zlib(TEXT);
+        let mut decoded = String::new();
+        Decompress::new(io::BufReader::with_capacity(1, data.as_slice()), false)
+            .expect("failed to create decoder")
+            .read_to_string(&mut decoded)
+            .expect("failed to decode");
+
+        assert_eq!(decoded, TEXT);
+    }
+
+    #[test]
+    fn test_gzip_multi_member() {
+        let mut data = Vec::new();
+        for _ in 0..2 {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(TEXT.as_bytes()).unwrap();
+            data.extend(encoder.finish().unwrap());
+        }
+
+        assert_eq!(decode(&data, false), TEXT.repeat(2));
+    }
+
+    #[test]
+    fn test_zlib_and_raw_deflate() {
+        assert_eq!(decode(&zlib(TEXT), false), TEXT);
+
+        let mut deflate = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        deflate.write_all(TEXT.as_bytes()).unwrap();
+        assert_eq!(decode(&deflate.finish().unwrap(), true), TEXT);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::cli::{Cli, DupsMode};
+use crate::progress::Progress;
+use crate::wc::{display_name, open_input};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Arc;
+
+const HLL_PRECISION: u32 = 14;
+const HEAVY_HITTERS: usize = 1024;
+
+#[derive(Serialize)]
+pub struct DupStats {
+    lines: usize,
+    distinct: usize,
+    approximate: bool,
+    top: Vec<(usize, String)>,
+    #[serde(rename = "name")]
+    file_name: String,
+}
+
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; 1 << HLL_PRECISION],
+        }
+    }
+
+    fn insert(&mut self, line: &str) {
+        let mut hasher = DefaultHasher::new();
+        line.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        let rank = ((hash << HLL_PRECISION).leading_zeros() + 1).min(64 - HLL_PRECISION + 1) as u8;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    fn estimate(&self) -> usize {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-i32::from(rank)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            estimate.round() as usize
+        }
+    }
+}
+
+// Misra-Gries summary: every line seen more than lines / (HEAVY_HITTERS + 1)
+// times keeps a counter, and kept counters never overestimate.
+struct HeavyHitters {
+    counters: HashMap<String, usize>,
+}
+
+impl HeavyHitters {
+    fn insert(&mut self, line: String) {
+        if let Some(count) = self.counters.get_mut(&line) {
+            *count += 1;
+        } else if self.counters.len() < HEAVY_HITTERS {
+            self.counters.insert(line, 1);
+        } else {
+            self.counters.retain(|_, count| {
+                *count -= 1;
+                *count > 0
+            });
+        }
+    }
+}
+
+enum LineCounter {
+    Exact(HashMap<String, usize>),
+    Approximate(HyperLogLog, HeavyHitters),
+}
+
+pub fn get_dup_statistics(
+    path: &Path,
+    args: &Cli,
+    mode: &DupsMode,
+    progress: Option<&Arc<Progress>>,
+) -> Result<DupStats, String> {
+    let reader = open_input(path, args, progress)?;
+
+    count_dups(reader, mode, args.dups_top, display_name(path, args))
+}
+
+fn count_dups<R: BufRead>(
+    reader: R,
+    mode: &DupsMode,
+    top: usize,
+    file_name: String,
+) -> Result<DupStats, String> {
+    let mut counter = match mode {
+        DupsMode::Exact => LineCounter::Exact(HashMap::new()),
+        DupsMode::Approx => LineCounter::Approximate(
+            HyperLogLog::new(),
+            HeavyHitters {
+                counters: HashMap::new(),
+            },
+        ),
+    };
+    let mut lines = 0;
+
+    for line in reader.lines() {
+        let line = line.map_err(|_| "error at reading line".to_string())?;
+        lines += 1;
+
+        match &mut counter {
+            LineCounter::Exact(counts) => *counts.entry(line).or_insert(0) += 1,
+            LineCounter::Approximate(hll, heavy_hitters) => {
+                hll.insert(&line);
+                heavy_hitters.insert(line);
+            }
+        }
+    }
+
+    let (distinct, counts) = match counter {
+        LineCounter::Exact(counts) => (counts.len(), counts),
+        LineCounter::Approximate(hll, heavy_hitters) => {
+            (hll.estimate().min(lines), heavy_hitters.counters)
+        }
+    };
+
+    let mut repeated: Vec<(usize, String)> = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(line, count)| (count, line))
+        .collect();
+    repeated.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    repeated.truncate(top);
+
+    Ok(DupStats {
+        lines,
+        distinct,
+        approximate: matches!(mode, DupsMode::Approx),
+        top: repeated,
+        file_name,
+    })
+}
+
+impl DupStats {
+    pub fn format(&self) -> String {
+        let marker = if self.approximate { "~" } else { "" };
+        let mut result = format!(
+            "{} lines {}{} unique {}{} duplicated {}\n",
+            self.lines,
+            marker,
+            self.distinct,
+            marker,
+            self.lines - self.distinct,
+            self.file_name
+        );
+        for (count, line) in &self.top {
+            result.push_str(&format!("{}{} {}\n", marker, count, line));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "GET /\nGET /a\nGET /\nPOST /b\nGET /\nGET /a\n";
+
+    #[test]
+    fn test_exact_counts() {
+        let stats = count_dups(LOG.as_bytes(), &DupsMode::Exact, 10, "log".to_string())
+            .expect("failed to count duplicates");
+
+        assert_eq!(
+            stats.format(),
+            "6 lines 3 unique 3 duplicated log\n3 GET /\n2 GET /a\n"
+        );
+    }
+
+    #[test]
+    fn test_dups_mode_needs_equals() {
+        use clap::Parser;
+
+        let args = Cli::parse_from(["wc", "--dups", "approx"]);
+        assert!(matches!(args.dups, Some(DupsMode::Exact)));
+        assert_eq!(args.paths, [std::path::PathBuf::from("approx")]);
+
+        let args = Cli::parse_from(["wc", "--dups=approx", "log"]);
+        assert!(matches!(args.dups, Some(DupsMode::Approx)));
+    }
+
+    #[test]
+    fn test_exact_top_limit() {
+        let stats = count_dups(LOG.as_bytes(), &DupsMode::Exact, 1, "log".to_string())
+            .expect("failed to count duplicates");
+
+        assert_eq!(stats.top, vec![(3, "GET /".to_string())]);
+    }
+
+    #[test]
+    fn test_approximate_counts() {
+        let data: String = (0..50_000)
+            .map(|i| format!("line {}\n", i % 20_000))
+            .collect();
+        let stats = count_dups(data.as_bytes(), &DupsMode::Approx, 3, "big".to_string())
+            .expect("failed to count duplicates");
+
+        assert_eq!(stats.lines, 50_000);
+        assert!(
+            (19_000..=21_000).contains(&stats.distinct),
+            "estimate {} too far from 20000",
+            stats.distinct
+        );
+        assert!(stats.format().starts_with("50000 lines ~"));
+    }
+
+    #[test]
+    fn test_heavy_hitters_keep_frequent_lines() {
+        let mut data = String::new();
+        for i in 0..20_000 {
+            data.push_str(&format!("noise {}\nhot\n", i));
+        }
+        let stats = count_dups(data.as_bytes(), &DupsMode::Approx, 1, "hot".to_string())
+            .expect("failed to count duplicates");
+
+        // Misra-Gries undercounts by at most lines / (counters + 1).
+        let bound = stats.lines / (HEAVY_HITTERS + 1);
+        assert_eq!(stats.top.len(), 1);
+        assert_eq!(stats.top[0].1, "hot");
+        assert!(
+            (20_000 - bound..=20_000).contains(&stats.top[0].0),
+            "count {} outside the error bound",
+            stats.top[0].0
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use crate::cli::Cli;
+use crate::decompress::{detect, Compression, HEADER_SIZE};
+use crate::special::has_reliable_size;
+use crate::wc::{display_name, ResultWc};
+use rand::Rng;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const CHUNK_SIZE: u64 = 64 * 1024;
+const Z_95: f64 = 1.96;
+
+// Half-widths of the 95% confidence intervals around the estimated counts.
+#[derive(Default, Serialize)]
+pub struct Estimate {
+    #[serde(rename = "lines_margin")]
+    pub lines: usize,
+    #[serde(rename = "words_margin")]
+    pub words: usize,
+    #[serde(rename = "chars_margin")]
+    pub chars: usize,
+    #[serde(rename = "bytes_margin")]
+    pub bytes: usize,
+    pub samples: usize,
+}
+
+impl Estimate {
+    // The files are sampled independently, so the margins of a total add up
+    // in quadrature rather than straight.
+    pub fn add(&mut self, other: &Estimate) {
+        let combine = |a: usize, b: usize| (a as f64).hypot(b as f64).round() as usize;
+        self.lines = combine(self.lines, other.lines);
+        self.words = combine(self.words, other.words);
+        self.chars = combine(self.chars, other.chars);
+        self.bytes = combine(self.bytes, other.bytes);
+        self.samples += other.samples;
+    }
+}
+
+struct Sample {
+    lines: f64,
+    words: f64,
+    chars: f64,
+    terminators: f64,
+    len: f64,
+}
+
+pub fn get_estimate(path: &Path, args: &Cli) -> Result<ResultWc, String> {
+    let mut file = File::open(path).map_err(|_| "Error at opening the file".to_string())?;
+    let metadata = file
+        .metadata()
+        .map_err(|_| "Error at reading the file".to_string())?;
+    if !metadata.is_file() {
+        return Err("--estimate needs a regular, seekable file".to_string());
+    }
+    if !has_reliable_size(&file, &metadata) {
+        return Err("--estimate needs a file that reports its size".to_string());
+    }
+
+    let mut header = Vec::new();
+    (&mut file)
+        .take(HEADER_SIZE)
+        .read_to_end(&mut header)
+        .map_err(|_| "Error at reading the file".to_string())?;
+    if !args.raw && detect(&header) != Compression::None {
+        return Err(
+            "--estimate cannot sample compressed input, use --raw to sample it as is".to_string(),
+        );
+    }
+
+    let samples = sample(
+        &mut file,
+        metadata.len(),
+        args.samples,
+        &mut rand::thread_rng(),
+    )
+    .map_err(|_| "Error at reading the file".to_string())?;
+
+    Ok(extrapolate(
+        &samples,
+        metadata.len(),
+        args,
+        display_name(path, args),
+    ))
+}
+
+// One chunk is read from a random offset inside each of `count` equal strata
+// of the file, which keeps the samples spread over the whole input.
+fn sample<R: Read + Seek>(
+    reader: &mut R,
+    size: u64,
+    count: usize,
+    rng: &mut impl Rng,
+) -> io::Result<Vec<Sample>> {
+    let count = count.max(1) as u64;
+    if size <= count * CHUNK_SIZE {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        return Ok(vec![scan(None, &buffer)]);
+    }
+
+    let stratum = size / count;
+    let mut samples = Vec::new();
+    let mut buffer = vec![0; CHUNK_SIZE as usize + 1];
+
+    for index in 0..count {
+        let start = index * stratum + rng.gen_range(0..=stratum - CHUNK_SIZE);
+        let (seek_to, chunk) = if start == 0 {
+            (0, &mut buffer[1..])
+        } else {
+            (start - 1, &mut buffer[..])
+        };
+        reader.seek(SeekFrom::Start(seek_to))?;
+        reader.read_exact(chunk)?;
+
+        let prev = if start == 0 { None } else { Some(buffer[0]) };
+        samples.push(scan(prev, &buffer[1..]));
+    }
+
+    Ok(samples)
+}
+
+fn scan(prev: Option<u8>, bytes: &[u8]) -> Sample {
+    let mut in_word = prev.is_some_and(|byte| !byte.is_ascii_whitespace());
+    let mut sample = Sample {
+        lines: 0.0,
+        words: 0.0,
+        chars: 0.0,
+        terminators: 0.0,
+        len: bytes.len() as f64,
+    };
+    let mut last = prev;
+
+    for &byte in bytes {
+        if byte == b'\n' {
+            sample.lines += 1.0;
+            sample.terminators += 1.0;
+            // The '\r' of a "\r\n" pair is part of the terminator.
+            if last == Some(b'\r') {
+                sample.chars -= 1.0;
+                sample.terminators += 1.0;
+            }
+        } else if byte & 0xc0 != 0x80 {
+            sample.chars += 1.0;
+        }
+
+        if byte.is_ascii_whitespace() {
+            in_word = false;
+        } else if !in_word {
+            in_word = true;
+            sample.words += 1.0;
+        }
+        last = Some(byte);
+    }
+
+    sample
+}
+
+// Returns the extrapolated total and the half-width of its 95% confidence
+// interval, using the spread of the per-chunk rates.
+fn project(samples: &[Sample], size: u64, value: impl Fn(&Sample) -> f64) -> (usize, usize) {
+    let size = size as f64;
+    let rates: Vec<f64> = samples
+        .iter()
+        .map(|sample| value(sample) / sample.len.max(1.0))
+        .collect();
+    let n = rates.len() as f64;
+    let mean = rates.iter().sum::<f64>() / n;
+
+    let sampled: f64 = samples.iter().map(|sample| sample.len).sum();
+    if n < 2.0 || sampled >= size {
+        return ((mean * size).round() as usize, 0);
+    }
+
+    let variance = rates.iter().map(|rate| (rate - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let correction = (1.0 - sampled / size).max(0.0).sqrt();
+    let margin = Z_95 * (variance / n).sqrt() * correction * size;
+
+    ((mean * size).round() as usize, margin.round() as usize)
+}
+
+fn extrapolate(samples: &[Sample], size: u64, args: &Cli, file_name: String) -> ResultWc {
+    let (lines, lines_margin) = project(samples, size, |sample| sample.lines);
+    let (words, words_margin) = project(samples, size, |sample| sample.words);
+    let (chars, chars_margin) = project(samples, size, |sample| sample.chars);
+    // Like the exact count, bytes leave out the line terminators.
+    let (terminators, terminators_margin) = project(samples, size, |sample| sample.terminators);
+    let bytes = (size as usize).saturating_sub(terminators);
+
+    let count_lines = args.count_lines || args.count_all;
+    let count_words = args.count_words || args.count_all;
+    let count_chars = args.count_chars || args.count_all;
+
+    ResultWc {
+        lines: if count_lines { lines } else { 0 },
+        words: if count_words { words } else { 0 },
+        chars: if count_chars { chars } else { 0 },
+        bytes: if args.count_bytes { bytes } else { 0 },
+        max_line_length: None,
+        tokens: None,
+        lang: None,
+        file_name,
+        path: PathBuf::new(),
+        size: size as usize,
+        estimate: Some(Estimate {
+            lines: lines_margin,
+            words: words_margin,
+            chars: chars_margin,
+            bytes: terminators_margin,
+            samples: samples.len(),
+        }),
+        code_lines: None,
+        classes: None,
+        range: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numbers::NumberStyle;
+    use clap::Parser;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::io::Cursor;
+
+    fn generate(lines: usize) -> (Vec<u8>, usize, usize) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut data = String::new();
+        let mut words = 0;
+        for _ in 0..lines {
+            let count = rng.gen_range(1..12);
+            words += count;
+            data.push_str(&vec!["lorem"; count].join(" "));
+            data.push('\n');
+        }
+        (data.into_bytes(), lines, words)
+    }
+
+    #[test]
+    fn test_estimate_within_confidence_interval() {
+        let (data, lines, words) = generate(400_000);
+        let size = data.len() as u64;
+        let samples = sample(
+            &mut Cursor::new(data),
+            size,
+            32,
+            &mut StdRng::seed_from_u64(1),
+        )
+        .expect("failed to sample");
+
+        let args = Cli::parse_from(["wc", "-l", "-w", "sample.txt"]);
+        let result = extrapolate(&samples, size, &args, "sample.txt".to_string());
+        let estimate = result.estimate.as_ref().expect("expected an estimate");
+
+        assert_eq!(estimate.samples, 32);
+        assert!(estimate.lines > 0 && estimate.words > 0);
+        assert!(result.lines.abs_diff(lines) <= estimate.lines);
+        assert!(result.words.abs_diff(words) <= estimate.words);
+        assert!(result
+            .format(&NumberStyle::Plain)
+            .ends_with("sample.txt (estimate, 32 samples)"));
+        assert!(result.format(&NumberStyle::Plain).starts_with('~'));
+    }
+
+    #[test]
+    fn test_total_of_estimates() {
+        let estimated = |lines, margin| ResultWc {
+            lines,
+            estimate: Some(Estimate {
+                lines: margin,
+                samples: 16,
+                ..Estimate::default()
+            }),
+            ..ResultWc::total()
+        };
+        let mut total = ResultWc::total();
+        total.add(&estimated(1000, 30));
+        total.add(&estimated(2000, 40));
+
+        assert_eq!(
+            total.format(&NumberStyle::Plain),
+            "~3000±50 total (estimate, 32 samples)"
+        );
+    }
+
+    #[test]
+    fn test_estimate_conflicts_with_unsampled_options() {
+        for option in [
+            "--word-delim=,",
+            "--record-sep=\\0",
+            "--classes",
+            "--prose",
+            "-L",
+        ] {
+            assert!(
+                Cli::try_parse_from(["wc", "--estimate", option, "a.txt"]).is_err(),
+                "{}",
+                option
+            );
+        }
+        assert!(Cli::try_parse_from(["wc", "--estimate", "-l", "a.txt"]).is_ok());
+    }
+
+    #[test]
+    fn test_estimate_small_input_is_exact() {
+        let data = "one two\nthree\n".as_bytes().to_vec();
+        let size = data.len() as u64;
+        let samples = sample(
+            &mut Cursor::new(data),
+            size,
+            8,
+            &mut StdRng::seed_from_u64(1),
+        )
+        .expect("failed to sample");
+
+        let args = Cli::parse_from(["wc", "-m", "small.txt"]);
+        let result = extrapolate(&samples, size, &args, "small.txt".to_string());
+
+        assert_eq!(
+            result.format(&NumberStyle::Plain),
+            "~2±0 ~3±0 ~12±0 small.txt (estimate, 1 samples)"
+        );
+    }
+}
@@ -0,0 +1,990 @@
+use flate2::bufread::ZlibDecoder;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::fs::FileExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+type Id = [u8; 20];
+
+// git itself refuses deeper delta chains.
+const MAX_DELTA_DEPTH: usize = 4095;
+const BASE_CACHE_SIZE: usize = 64 << 20;
+
+// Repositories are opened once per process and shared by all the paths in it.
+// Only what cannot change is kept: refs are resolved again on every lookup,
+// and the packs are listed again when an object is not found.
+static REPOS: Mutex<BTreeMap<PathBuf, Arc<Repo>>> = Mutex::new(BTreeMap::new());
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+enum Entry {
+    Tree(Id),
+    Blob(Id),
+    Other,
+    Missing,
+}
+
+// The pack stays open, it can still be read after a gc removed it.
+struct Pack {
+    index: Vec<u8>,
+    count: usize,
+    index_path: PathBuf,
+    file: File,
+    bases: Mutex<Bases>,
+}
+
+// Delta bases already resolved, dropped all at once when they grow too big.
+#[derive(Default)]
+struct Bases {
+    objects: HashMap<u64, (Kind, Vec<u8>)>,
+    size: usize,
+}
+
+enum Found {
+    Loose(Kind, Vec<u8>),
+    Packed(Arc<Pack>, u64),
+}
+
+enum Packed {
+    Whole(Kind, Vec<u8>),
+    OfsDelta(u64, Vec<u8>),
+    RefDelta(Id, Vec<u8>),
+}
+
+// Reads from an offset without a shared cursor, so workers can read the same
+// pack at once.
+struct PackReader<'a> {
+    file: &'a File,
+    offset: u64,
+}
+
+struct Repo {
+    git_dir: PathBuf,
+    common_dir: PathBuf,
+    packs: Mutex<Vec<Arc<Pack>>>,
+    trees: Mutex<HashMap<Id, Id>>,
+}
+
+// With -r, directories of the revision are expanded into the files below
+// them. Everything else is left for read_blob to report on.
+pub fn collect_rev_paths(paths: &[PathBuf], rev: &str, recursive: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !recursive {
+            files.push(path.clone());
+            continue;
+        }
+        match list(path, rev) {
+            Ok(Some(mut listed)) => files.append(&mut listed),
+            Ok(None) => files.push(path.clone()),
+            Err(err) => eprintln!("{}: {}", path.display(), err),
+        }
+    }
+
+    files
+}
+
+pub fn read_blob(path: &Path, rev: &str) -> Result<Vec<u8>, String> {
+    let (repo, names) = locate(path)?;
+    match repo.lookup(rev, &names)? {
+        Entry::Blob(id) => match repo.read(&id)? {
+            (Kind::Blob, data) => Ok(data),
+            _ => Err(corrupt()),
+        },
+        Entry::Tree(_) => Err("Is a directory".to_string()),
+        Entry::Other => Err("Is not a regular file".to_string()),
+        Entry::Missing => Err(format!("No such file at {}", rev)),
+    }
+}
+
+fn list(path: &Path, rev: &str) -> Result<Option<Vec<PathBuf>>, String> {
+    let (repo, names) = locate(path)?;
+    let Entry::Tree(tree) = repo.lookup(rev, &names)? else {
+        return Ok(None);
+    };
+
+    let mut files = Vec::new();
+    repo.walk(&tree, path, &mut files)?;
+    files.sort();
+
+    Ok(Some(files))
+}
+
+// Finds the repository holding the path and the path's components inside it.
+// The path does not need to exist in the working tree.
+fn locate(path: &Path) -> Result<(Arc<Repo>, Vec<String>), String> {
+    let current_dir =
+        std::env::current_dir().map_err(|_| "Error at reading the current directory")?;
+    let mut absolute = PathBuf::new();
+    for component in current_dir.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+
+    let mut worktree = absolute.as_path();
+    while !worktree.join(".git").exists() {
+        worktree = worktree
+            .parent()
+            .ok_or_else(|| "Not inside a git repository".to_string())?;
+    }
+    let names = absolute
+        .strip_prefix(worktree)
+        .expect("the worktree is an ancestor of the path")
+        .components()
+        .map(|component| component.as_os_str().to_str().map(str::to_string))
+        .collect::<Option<_>>()
+        .ok_or_else(|| "Path is not valid UTF-8".to_string())?;
+
+    let mut repos = REPOS.lock().unwrap();
+    let repo = match repos.get(worktree) {
+        Some(repo) => repo.clone(),
+        None => {
+            let repo = Arc::new(Repo::open(worktree)?);
+            repos.insert(worktree.to_path_buf(), repo.clone());
+            repo
+        }
+    };
+
+    Ok((repo, names))
+}
+
+impl Repo {
+    // Linked worktrees have a .git file pointing at their git dir, which in
+    // turn points at the shared objects and refs through `commondir`.
+    fn open(worktree: &Path) -> Result<Self, String> {
+        let dot_git = worktree.join(".git");
+        let git_dir = if dot_git.is_file() {
+            let content =
+                fs::read_to_string(&dot_git).map_err(|_| "Error at reading the .git file")?;
+            let target = content
+                .trim()
+                .strip_prefix("gitdir: ")
+                .ok_or_else(|| "Invalid .git file".to_string())?;
+            worktree.join(target)
+        } else {
+            dot_git
+        };
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) => git_dir.join(common_dir.trim()),
+            Err(_) => git_dir.clone(),
+        };
+
+        let repo = Repo {
+            git_dir,
+            common_dir,
+            packs: Mutex::new(Vec::new()),
+            trees: Mutex::new(HashMap::new()),
+        };
+        repo.load_packs()?;
+
+        Ok(repo)
+    }
+
+    // Packs that are still there are kept, new ones are opened.
+    fn load_packs(&self) -> Result<(), String> {
+        let mut indexes: Vec<PathBuf> = match fs::read_dir(self.common_dir.join("objects/pack")) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        indexes.sort();
+
+        let mut packs = self.packs.lock().unwrap();
+        let mut loaded = Vec::new();
+        for index in indexes {
+            match packs.iter().find(|pack| pack.index_path == index) {
+                Some(pack) => loaded.push(pack.clone()),
+                None => loaded.push(Arc::new(Pack::open(&index)?)),
+            }
+        }
+        *packs = loaded;
+
+        Ok(())
+    }
+
+    fn packs(&self) -> Vec<Arc<Pack>> {
+        self.packs.lock().unwrap().clone()
+    }
+
+    // Full ids win over refs, refs over abbreviated ids, like in git.
+    fn resolve(&self, rev: &str) -> Result<Id, String> {
+        if let Some(id) = parse_hex(rev) {
+            return Ok(id);
+        }
+        if rev.split('/').any(|part| part.is_empty() || part == "..") {
+            return Err(format!("Unknown revision {}", rev));
+        }
+
+        let mut names = vec![
+            format!("refs/{}", rev),
+            format!("refs/tags/{}", rev),
+            format!("refs/heads/{}", rev),
+            format!("refs/remotes/{}", rev),
+            format!("refs/remotes/{}/HEAD", rev),
+        ];
+        if rev.starts_with("refs/") || rev.chars().all(|c| c.is_ascii_uppercase() || c == '_') {
+            names.insert(0, rev.to_string());
+        }
+        for name in names {
+            if let Some(id) = self.read_ref(&name, 0)? {
+                return Ok(id);
+            }
+        }
+
+        if rev.len() >= 4 && rev.chars().all(|c| c.is_ascii_hexdigit()) {
+            let prefix = rev.to_ascii_lowercase();
+            let mut matches = self.abbreviated(&prefix);
+            if matches.is_empty() {
+                self.load_packs()?;
+                matches = self.abbreviated(&prefix);
+            }
+            match matches.len() {
+                1 => return Ok(matches[0]),
+                0 => {}
+                _ => return Err(format!("Ambiguous revision {}", rev)),
+            }
+        }
+
+        Err(format!("Unknown revision {}", rev))
+    }
+
+    // HEAD and the other top-level refs belong to the worktree, everything
+    // under refs/ is shared.
+    fn read_ref(&self, name: &str, depth: usize) -> Result<Option<Id>, String> {
+        if depth > 10 {
+            return Err(format!("Too many levels of symbolic refs at {}", name));
+        }
+        let dir = if name.starts_with("refs/") {
+            &self.common_dir
+        } else {
+            &self.git_dir
+        };
+
+        if let Ok(content) = fs::read_to_string(dir.join(name)) {
+            let content = content.trim();
+            if let Some(target) = content.strip_prefix("ref: ") {
+                return self.read_ref(target, depth + 1);
+            }
+            return parse_hex(content)
+                .map(Some)
+                .ok_or_else(|| format!("Invalid ref {}", name));
+        }
+
+        let packed = fs::read_to_string(self.common_dir.join("packed-refs")).unwrap_or_default();
+        for line in packed.lines() {
+            if line.starts_with('#') || line.starts_with('^') {
+                continue;
+            }
+            if let Some((id, packed_name)) = line.split_once(' ') {
+                if packed_name == name {
+                    return parse_hex(id)
+                        .map(Some)
+                        .ok_or_else(|| format!("Invalid ref {}", name));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn abbreviated(&self, prefix: &str) -> Vec<Id> {
+        let mut matches = Vec::new();
+        let dir = self.common_dir.join("objects").join(&prefix[..2]);
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let hex = format!("{}{}", &prefix[..2], entry.file_name().to_string_lossy());
+                if hex.starts_with(prefix) {
+                    matches.extend(parse_hex(&hex));
+                }
+            }
+        }
+        for pack in self.packs() {
+            matches.extend(pack.abbreviated(prefix));
+        }
+        matches.sort();
+        matches.dedup();
+
+        matches
+    }
+
+    // Annotated tags and commits are peeled down to their tree. What an id
+    // peels to never changes, so that is cached.
+    fn tree(&self, rev: &str) -> Result<Id, String> {
+        let resolved = self.resolve(rev)?;
+        if let Some(tree) = self.trees.lock().unwrap().get(&resolved) {
+            return Ok(*tree);
+        }
+
+        let mut id = resolved;
+        loop {
+            let (kind, data) = self.read(&id)?;
+            id = match kind {
+                Kind::Tree => break,
+                Kind::Commit => header(&data, "tree")?,
+                Kind::Tag => header(&data, "object")?,
+                Kind::Blob => return Err(format!("{} is not a commit", rev)),
+            };
+        }
+        self.trees.lock().unwrap().insert(resolved, id);
+
+        Ok(id)
+    }
+
+    fn lookup(&self, rev: &str, names: &[String]) -> Result<Entry, String> {
+        let mut entry = Entry::Tree(self.tree(rev)?);
+        for name in names {
+            let Entry::Tree(tree) = entry else {
+                return Ok(Entry::Missing);
+            };
+            entry = self
+                .entries(&tree)?
+                .into_iter()
+                .find(|(entry_name, _)| entry_name == name)
+                .map_or(Entry::Missing, |(_, entry)| entry);
+        }
+
+        Ok(entry)
+    }
+
+    // Symlinks and submodules are skipped, there is no file to count.
+    fn walk(&self, tree: &Id, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+        for (name, entry) in self.entries(tree)? {
+            match entry {
+                Entry::Tree(tree) => self.walk(&tree, &dir.join(name), files)?,
+                Entry::Blob(_) => files.push(dir.join(name)),
+                Entry::Other | Entry::Missing => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entries(&self, tree: &Id) -> Result<Vec<(String, Entry)>, String> {
+        let (Kind::Tree, data) = self.read(tree)? else {
+            return Err(corrupt());
+        };
+
+        let mut entries = Vec::new();
+        let mut rest = data.as_slice();
+        while !rest.is_empty() {
+            let space = rest.iter().position(|&b| b == b' ').ok_or_else(corrupt)?;
+            let nul = rest.iter().position(|&b| b == 0).ok_or_else(corrupt)?;
+            let name = rest.get(space + 1..nul).ok_or_else(corrupt)?;
+            let id: Id = rest
+                .get(nul + 1..nul + 21)
+                .and_then(|id| id.try_into().ok())
+                .ok_or_else(corrupt)?;
+            let entry = match &rest[..space] {
+                b"40000" => Entry::Tree(id),
+                b"100644" | b"100755" => Entry::Blob(id),
+                _ => Entry::Other,
+            };
+            let name = String::from_utf8_lossy(name).into_owned();
+            entries.push((name, entry));
+            rest = &rest[nul + 21..];
+        }
+
+        Ok(entries)
+    }
+
+    // Delta chains are followed down to a whole object, or a base read
+    // before, and the deltas are then applied on the way back up.
+    fn read(&self, id: &Id) -> Result<(Kind, Vec<u8>), String> {
+        let (mut pack, mut offset) = match self.find(id)? {
+            Found::Loose(kind, data) => return Ok((kind, data)),
+            Found::Packed(pack, offset) => (pack, offset),
+        };
+
+        let mut deltas = Vec::new();
+        let (kind, mut data) = loop {
+            if let Some(base) = pack.cached(offset) {
+                break base;
+            }
+            if deltas.len() > MAX_DELTA_DEPTH {
+                return Err(corrupt());
+            }
+            match pack.object(offset)? {
+                Packed::Whole(kind, data) => {
+                    if !deltas.is_empty() {
+                        pack.cache(offset, kind, &data);
+                    }
+                    break (kind, data);
+                }
+                Packed::OfsDelta(base, delta) => {
+                    deltas.push((pack.clone(), offset, delta));
+                    offset = base;
+                }
+                Packed::RefDelta(base, delta) => {
+                    deltas.push((pack.clone(), offset, delta));
+                    match self.find(&base)? {
+                        Found::Loose(kind, data) => break (kind, data),
+                        Found::Packed(base_pack, base_offset) => {
+                            pack = base_pack;
+                            offset = base_offset;
+                        }
+                    }
+                }
+            }
+        };
+        while let Some((pack, offset, delta)) = deltas.pop() {
+            data = apply_delta(&data, &delta)?;
+            if !deltas.is_empty() {
+                pack.cache(offset, kind, &data);
+            }
+        }
+
+        Ok((kind, data))
+    }
+
+    fn find(&self, id: &Id) -> Result<Found, String> {
+        let hex = to_hex(id);
+        let loose = self
+            .common_dir
+            .join("objects")
+            .join(&hex[..2])
+            .join(&hex[2..]);
+        if let Ok(file) = File::open(loose) {
+            let mut data = Vec::new();
+            ZlibDecoder::new(BufReader::new(file))
+                .read_to_end(&mut data)
+                .map_err(|_| corrupt())?;
+            let nul = data.iter().position(|&b| b == 0).ok_or_else(corrupt)?;
+            let kind = match data[..nul].split(|&b| b == b' ').next() {
+                Some(b"commit") => Kind::Commit,
+                Some(b"tree") => Kind::Tree,
+                Some(b"blob") => Kind::Blob,
+                Some(b"tag") => Kind::Tag,
+                _ => return Err(corrupt()),
+            };
+            return Ok(Found::Loose(kind, data.split_off(nul + 1)));
+        }
+
+        // A commit or a gc since the packs were listed may have added or
+        // replaced some.
+        for reload in [false, true] {
+            if reload {
+                self.load_packs()?;
+            }
+            for pack in self.packs() {
+                if let Some(offset) = pack.find(id) {
+                    return Ok(Found::Packed(pack, offset));
+                }
+            }
+        }
+
+        Err(format!("Missing object {}", hex))
+    }
+}
+
+impl Pack {
+    // Only version 2 indexes are supported, git has written them since 1.5.2.
+    fn open(path: &Path) -> Result<Self, String> {
+        let index = fs::read(path).map_err(|_| "Error at reading the pack index")?;
+        if index.len() < 8 + 256 * 4 || index[..8] != [0xff, b't', b'O', b'c', 0, 0, 0, 2] {
+            return Err(format!("Unsupported pack index {}", path.display()));
+        }
+
+        let file =
+            File::open(path.with_extension("pack")).map_err(|_| "Error at opening the pack")?;
+        let mut pack = Pack {
+            index,
+            count: 0,
+            index_path: path.to_path_buf(),
+            file,
+            bases: Mutex::new(Bases::default()),
+        };
+        pack.count = pack.fanout(255);
+        let sorted = (1..256).all(|byte| pack.fanout(byte - 1) <= pack.fanout(byte));
+        if !sorted || pack.index.len() < 8 + 256 * 4 + pack.count * 28 {
+            return Err(format!("Unsupported pack index {}", path.display()));
+        }
+
+        Ok(pack)
+    }
+
+    fn object(&self, offset: u64) -> Result<Packed, String> {
+        let mut reader = BufReader::new(PackReader {
+            file: &self.file,
+            offset,
+        });
+
+        let mut byte = read_byte(&mut reader)?;
+        let kind = (byte >> 4) & 7;
+        let mut size = (byte & 0x0f) as u64;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = read_byte(&mut reader)?;
+            size |= shifted((byte & 0x7f) as u64, shift)?;
+            shift += 7;
+        }
+
+        match kind {
+            1 => Ok(Packed::Whole(Kind::Commit, inflate(&mut reader, size)?)),
+            2 => Ok(Packed::Whole(Kind::Tree, inflate(&mut reader, size)?)),
+            3 => Ok(Packed::Whole(Kind::Blob, inflate(&mut reader, size)?)),
+            4 => Ok(Packed::Whole(Kind::Tag, inflate(&mut reader, size)?)),
+            6 => {
+                let mut byte = read_byte(&mut reader)?;
+                let mut distance = (byte & 0x7f) as u64;
+                while byte & 0x80 != 0 {
+                    byte = read_byte(&mut reader)?;
+                    distance = distance
+                        .checked_add(1)
+                        .and_then(|distance| distance.checked_mul(0x80))
+                        .ok_or_else(corrupt)?
+                        | (byte & 0x7f) as u64;
+                }
+                // The base always comes earlier in the pack.
+                let base = offset
+                    .checked_sub(distance)
+                    .filter(|_| distance > 0)
+                    .ok_or_else(corrupt)?;
+                Ok(Packed::OfsDelta(base, inflate(&mut reader, size)?))
+            }
+            7 => {
+                let mut base: Id = [0; 20];
+                reader.read_exact(&mut base).map_err(|_| corrupt())?;
+                Ok(Packed::RefDelta(base, inflate(&mut reader, size)?))
+            }
+            _ => Err(corrupt()),
+        }
+    }
+
+    fn cached(&self, offset: u64) -> Option<(Kind, Vec<u8>)> {
+        self.bases.lock().unwrap().objects.get(&offset).cloned()
+    }
+
+    fn cache(&self, offset: u64, kind: Kind, data: &[u8]) {
+        let mut bases = self.bases.lock().unwrap();
+        if bases.size + data.len() > BASE_CACHE_SIZE {
+            *bases = Bases::default();
+        }
+        if data.len() <= BASE_CACHE_SIZE {
+            bases.size += data.len();
+            bases.objects.insert(offset, (kind, data.to_vec()));
+        }
+    }
+
+    fn fanout(&self, byte: usize) -> usize {
+        be_u32(&self.index[8 + byte * 4..]) as usize
+    }
+
+    fn name(&self, position: usize) -> &[u8] {
+        let start = 8 + 256 * 4 + position * 20;
+        &self.index[start..start + 20]
+    }
+
+    // Positions of the ids starting with the given byte.
+    fn bucket(&self, byte: u8) -> std::ops::Range<usize> {
+        let start = match byte {
+            0 => 0,
+            byte => self.fanout(byte as usize - 1),
+        };
+        start..self.fanout(byte as usize)
+    }
+
+    fn find(&self, id: &Id) -> Option<u64> {
+        let bucket = self.bucket(id[0]);
+        let (mut low, mut high) = (bucket.start, bucket.end);
+        while low < high {
+            let middle = (low + high) / 2;
+            match self.name(middle).cmp(id) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => return self.offset(middle),
+            }
+        }
+
+        None
+    }
+
+    fn abbreviated(&self, prefix: &str) -> Vec<Id> {
+        let Ok(first) = u8::from_str_radix(&prefix[..2], 16) else {
+            return Vec::new();
+        };
+        self.bucket(first)
+            .map(|position| self.name(position))
+            .filter(|name| to_hex(name).starts_with(prefix))
+            .filter_map(|name| name.try_into().ok())
+            .collect()
+    }
+
+    // Offsets past 2 GiB are stored in a separate table of 64-bit values.
+    fn offset(&self, position: usize) -> Option<u64> {
+        let table = 8 + 256 * 4 + self.count * 24;
+        let offset = be_u32(&self.index[table + position * 4..]);
+        if offset & 0x8000_0000 == 0 {
+            return Some(offset as u64);
+        }
+
+        let large = table + self.count * 4 + (offset & 0x7fff_ffff) as usize * 8;
+        let bytes = self.index.get(large..large + 8)?;
+        Some(u64::from_be_bytes(bytes.try_into().ok()?))
+    }
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, String> {
+    let mut position = 0;
+    let source_size = delta_size(delta, &mut position)?;
+    let target_size = delta_size(delta, &mut position)?;
+    if source_size != base.len() {
+        return Err(corrupt());
+    }
+
+    let mut target = Vec::with_capacity(target_size.min(base.len() + delta.len()));
+    let next = |position: &mut usize| {
+        let byte = delta.get(*position).copied().ok_or_else(corrupt);
+        *position += 1;
+        byte
+    };
+    while position < delta.len() {
+        let op = next(&mut position)?;
+        if op & 0x80 != 0 {
+            let mut offset = 0;
+            let mut size = 0;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (next(&mut position)? as usize) << (8 * i);
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    size |= (next(&mut position)? as usize) << (8 * i);
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            let copy = base.get(offset..offset + size).ok_or_else(corrupt)?;
+            target.extend_from_slice(copy);
+        } else if op != 0 {
+            let insert = delta
+                .get(position..position + op as usize)
+                .ok_or_else(corrupt)?;
+            target.extend_from_slice(insert);
+            position += op as usize;
+        } else {
+            return Err(corrupt());
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(corrupt());
+    }
+
+    Ok(target)
+}
+
+fn delta_size(delta: &[u8], position: &mut usize) -> Result<usize, String> {
+    let mut size = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *delta.get(*position).ok_or_else(corrupt)?;
+        *position += 1;
+        size |= shifted((byte & 0x7f) as u64, shift)? as usize;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+impl Read for PackReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.file.read_at(buf, self.offset)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+}
+
+fn inflate<R: BufRead>(reader: R, size: u64) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    ZlibDecoder::new(reader)
+        .take(size)
+        .read_to_end(&mut data)
+        .map_err(|_| corrupt())?;
+    if data.len() as u64 != size {
+        return Err(corrupt());
+    }
+
+    Ok(data)
+}
+
+// Sizes are little-endian groups of 7 bits, corrupt ones can be too long
+// for 64 bits.
+fn shifted(value: u64, shift: u32) -> Result<u64, String> {
+    value
+        .checked_shl(shift)
+        .filter(|shifted| shifted >> shift == value)
+        .ok_or_else(corrupt)
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> Result<u8, String> {
+    let mut byte = [0; 1];
+    reader.read_exact(&mut byte).map_err(|_| corrupt())?;
+    Ok(byte[0])
+}
+
+fn header(data: &[u8], key: &str) -> Result<Id, String> {
+    for line in data.split(|&b| b == b'\n') {
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix(format!("{} ", key).as_bytes()) {
+            return std::str::from_utf8(value)
+                .ok()
+                .and_then(parse_hex)
+                .ok_or_else(corrupt);
+        }
+    }
+
+    Err(corrupt())
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn to_hex(id: &[u8]) -> String {
+    id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_hex(hex: &str) -> Option<Id> {
+    if hex.len() != 40 {
+        return None;
+    }
+    let mut id = [0; 20];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+
+    Some(id)
+}
+
+fn corrupt() -> String {
+    "Corrupt git object store".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(repo: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(["-c", "user.name=wc", "-c", "user.email=wc@example.com"])
+            .args(["-c", "commit.gpgsign=false", "-c", "tag.gpgsign=false"])
+            .args(args)
+            .current_dir(repo)
+            .output()
+            .expect("failed to run git");
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8(output.stdout).expect("invalid git output")
+    }
+
+    // Two commits: v1 has 200 numbered lines in src/a.txt, the second commit
+    // changes one of them so a repack can store it as a delta.
+    fn fixture(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("wc_git_{}_{}", std::process::id(), name));
+        fs::create_dir_all(root.join("src/nested")).expect("failed to create test repo");
+        git(&root, &["init", "-q"]);
+
+        let mut text: String = (0..200).map(|i| format!("line {} of text\n", i)).collect();
+        fs::write(root.join("src/a.txt"), &text).expect("failed to write test file");
+        fs::write(root.join("src/nested/b.txt"), "one two\n").expect("failed to write test file");
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "-q", "-m", "first"]);
+        git(&root, &["tag", "-a", "v1", "-m", "release"]);
+
+        text = text.replace("line 100 of text\n", "changed\n");
+        fs::write(root.join("src/a.txt"), &text).expect("failed to write test file");
+        fs::remove_file(root.join("src/nested/b.txt")).expect("failed to remove test file");
+        git(&root, &["commit", "-q", "-am", "second"]);
+
+        root
+    }
+
+    fn read(root: &Path, path: &str, rev: &str) -> Result<String, String> {
+        read_blob(&root.join(path), rev).map(|data| String::from_utf8(data).unwrap())
+    }
+
+    #[test]
+    fn test_loose_objects() {
+        let root = fixture("loose");
+        let head = git(&root, &["rev-parse", "HEAD"]);
+
+        let old = read(&root, "src/a.txt", "v1");
+        let new = read(&root, "src/a.txt", head.trim());
+        let short = read(&root, "src/a.txt", &head[..7]);
+        let removed = read(&root, "src/nested/b.txt", "HEAD");
+        let listed = collect_rev_paths(&[root.join("src")], "v1", true);
+        fs::remove_dir_all(&root).expect("error when removing test repo");
+
+        assert_eq!(old.unwrap().lines().count(), 200);
+        assert!(new.as_ref().unwrap().contains("changed\n"));
+        assert_eq!(short, new);
+        assert_eq!(removed, Err("No such file at HEAD".to_string()));
+        assert_eq!(
+            listed,
+            vec![root.join("src/a.txt"), root.join("src/nested/b.txt")]
+        );
+    }
+
+    #[test]
+    fn test_packed_objects_and_refs() {
+        let root = fixture("packed");
+        let expected_old = fs::read_to_string(root.join("src/a.txt"))
+            .unwrap()
+            .replace("changed\n", "line 100 of text\n");
+        git(&root, &["gc", "-q", "--aggressive", "--prune=now"]);
+        let objects = git(&root, &["count-objects", "-v"]);
+        let verify = git(&root, &["verify-pack", "-v", &pack_index(&root)]);
+
+        let old = read(&root, "src/a.txt", "v1");
+        let new = read(&root, "src/a.txt", "HEAD");
+        let directory = read(&root, "src/nested", "tags/v1");
+        fs::remove_dir_all(&root).expect("error when removing test repo");
+
+        assert!(objects.contains("count: 0"), "objects were not packed");
+        assert!(verify.contains("chain length"), "no deltas in the pack");
+        assert_eq!(old, Ok(expected_old));
+        assert!(new.unwrap().contains("changed\n"));
+        assert_eq!(directory, Err("Is a directory".to_string()));
+    }
+
+    #[test]
+    fn test_deep_delta_chain() {
+        use rand::{Rng, SeedableRng};
+
+        // Every version rewrites one more line with random text, so each one
+        // is closest to the one before and the repack chains them all.
+        let root = fixture("deep");
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut line = |version: usize| format!("{} {:032x}\n", version, rng.gen::<u128>());
+        let mut lines: Vec<String> = (0..60).map(&mut line).collect();
+        let mut versions = Vec::new();
+        for version in 0..60 {
+            lines[version] = line(version);
+            let text = lines.concat();
+            fs::write(root.join("src/a.txt"), &text).expect("failed to write test file");
+            git(&root, &["commit", "-q", "-am", "next"]);
+            let commit = git(&root, &["rev-parse", "HEAD"]).trim().to_string();
+            versions.push((commit, text));
+        }
+        git(
+            &root,
+            &[
+                "repack",
+                "-q",
+                "-a",
+                "-d",
+                "-f",
+                "--depth=4095",
+                "--window=250",
+            ],
+        );
+
+        // Chains are not followed by recursion, so a small stack is enough.
+        let reader = {
+            let (root, versions) = (root.clone(), versions.clone());
+            std::thread::Builder::new()
+                .stack_size(256 * 1024)
+                .spawn(move || {
+                    versions
+                        .iter()
+                        .map(|(commit, _)| read(&root, "src/a.txt", commit))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap()
+        };
+        let read = reader.join().unwrap();
+        let chains = git(&root, &["verify-pack", "-v", &pack_index(&root)]);
+        fs::remove_dir_all(&root).expect("error when removing test repo");
+
+        assert!(chains.contains("chain length = 50"), "{}", chains);
+        for (text, (_, expected)) in read.into_iter().zip(versions) {
+            assert_eq!(text, Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_new_commits_and_packs() {
+        let root = fixture("moving");
+        let before = read(&root, "src/a.txt", "HEAD");
+
+        fs::write(root.join("src/a.txt"), "third\n").expect("failed to write test file");
+        git(&root, &["commit", "-q", "-am", "third"]);
+        let after = read(&root, "src/a.txt", "HEAD");
+        // The old objects are only left in a pack that did not exist before.
+        git(&root, &["gc", "-q", "--prune=now"]);
+        let old = read(&root, "src/a.txt", "v1");
+        fs::remove_dir_all(&root).expect("error when removing test repo");
+
+        assert!(before.unwrap().contains("changed\n"));
+        assert_eq!(after, Ok("third\n".to_string()));
+        assert_eq!(old.unwrap().lines().count(), 200);
+    }
+
+    #[test]
+    fn test_corrupt_objects() {
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+
+        let root = fixture("corrupt");
+        // The NUL that ends the mode comes before the space.
+        let id = [0xab; 20];
+        let dir = root.join(".git/objects/ab");
+        fs::create_dir_all(&dir).expect("failed to create object dir");
+        let mut encoder = ZlibEncoder::new(
+            File::create(dir.join(&to_hex(&id)[2..])).expect("failed to create object"),
+            flate2::Compression::default(),
+        );
+        encoder
+            .write_all(b"tree 10\x00100644\0a b")
+            .expect("failed to write object");
+        encoder.finish().expect("failed to finish object");
+
+        let (repo, _) = locate(&root).expect("failed to open test repo");
+        let entries = repo.entries(&id).map(|entries| entries.len());
+        fs::remove_dir_all(&root).expect("error when removing test repo");
+
+        assert_eq!(entries, Err(corrupt()));
+    }
+
+    fn pack_index(root: &Path) -> String {
+        fs::read_dir(root.join(".git/objects/pack"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "idx"))
+            .expect("no pack index")
+            .display()
+            .to_string()
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        let base = b"hello world";
+        // Sizes 11 and 13, copy 6 bytes from offset 0, then insert 7 bytes.
+        let delta = [11, 13, 0x90, 6, 7, b't', b'h', b'e', b'r', b'e', b' ', b'w'];
+
+        assert_eq!(apply_delta(base, &delta), Ok(b"hello there w".to_vec()));
+        assert!(apply_delta(b"short", &delta).is_err());
+        // A size that goes on for more than 64 bits.
+        assert_eq!(apply_delta(base, &[0xff; 12]), Err(corrupt()));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::wc::ResultWc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GroupBy {
+    Extension,
+    Directory,
+    Language,
+    Depth(usize),
+}
+
+#[derive(Serialize)]
+pub struct Group {
+    pub files: usize,
+    #[serde(flatten)]
+    pub result: ResultWc,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "ext" => Ok(GroupBy::Extension),
+            "dir" => Ok(GroupBy::Directory),
+            "lang" => Ok(GroupBy::Language),
+            _ => value
+                .strip_prefix("depth:")
+                .and_then(|depth| depth.parse().ok())
+                .map(GroupBy::Depth)
+                .ok_or_else(|| "expected ext, dir, lang or depth:N".to_string()),
+        }
+    }
+}
+
+impl GroupBy {
+    fn key(&self, path: &Path) -> String {
+        // ./src and src are the same directory.
+        let parent = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .components()
+            .filter(|component| !matches!(component, Component::CurDir));
+
+        let key = match self {
+            GroupBy::Extension => path
+                .extension()
+                .map(|ext| format!(".{}", ext.to_string_lossy())),
+            GroupBy::Directory => Some(parent.collect::<PathBuf>().display().to_string()),
+            GroupBy::Language => path
+                .extension()
+                .and_then(|ext| language(&ext.to_string_lossy().to_lowercase()))
+                .map(str::to_string),
+            GroupBy::Depth(depth) => Some(
+                parent
+                    .take(*depth)
+                    .collect::<PathBuf>()
+                    .display()
+                    .to_string(),
+            ),
+        };
+
+        match key {
+            Some(key) if !key.is_empty() => key,
+            Some(_) if matches!(self, GroupBy::Directory | GroupBy::Depth(_)) => ".".to_string(),
+            _ => "(other)".to_string(),
+        }
+    }
+}
+
+fn language(extension: &str) -> Option<&'static str> {
+    let language = match extension {
+        "rs" => "Rust",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "py" => "Python",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "cs" => "C#",
+        "swift" => "Swift",
+        "sh" | "bash" | "zsh" => "Shell",
+        "html" | "htm" => "HTML",
+        "css" | "scss" => "CSS",
+        "md" | "markdown" => "Markdown",
+        "adoc" | "asciidoc" => "AsciiDoc",
+        "json" => "JSON",
+        "toml" => "TOML",
+        "yml" | "yaml" => "YAML",
+        "xml" => "XML",
+        "sql" => "SQL",
+        "txt" => "Text",
+        "csv" | "tsv" => "CSV",
+        _ => return None,
+    };
+
+    Some(language)
+}
+
+pub fn group(results: &[ResultWc], by: &GroupBy) -> Vec<Group> {
+    let mut groups: BTreeMap<String, Group> = BTreeMap::new();
+    for result in results {
+        let key = by.key(&result.path);
+        let group = groups.entry(key.clone()).or_insert_with(|| {
+            let mut total = ResultWc::total();
+            total.file_name = key;
+            Group {
+                files: 0,
+                result: total,
+            }
+        });
+        group.files += 1;
+        group.result.add(result);
+    }
+
+    groups.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(file_name: &str, lines: usize) -> ResultWc {
+        let mut result = ResultWc::total();
+        result.path = PathBuf::from(file_name);
+        result.lines = lines;
+        result
+    }
+
+    fn summary(by: &str) -> Vec<(String, usize, usize)> {
+        let results = vec![
+            result("src/main.rs", 10),
+            result("src/cli/args.rs", 5),
+            result("README.md", 3),
+            result("./src/cli/help.md", 2),
+            result("Makefile", 1),
+        ];
+
+        group(&results, &by.parse().expect("invalid group"))
+            .into_iter()
+            .map(|group| (group.result.file_name, group.files, group.result.lines))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_group_by() {
+        assert_eq!("depth:2".parse(), Ok(GroupBy::Depth(2)));
+        assert_eq!("ext".parse(), Ok(GroupBy::Extension));
+        assert!("depth:x".parse::<GroupBy>().is_err());
+        assert!("size".parse::<GroupBy>().is_err());
+    }
+
+    #[test]
+    fn test_group_by_extension_and_language() {
+        assert_eq!(
+            summary("ext"),
+            vec![
+                ("(other)".to_string(), 1, 1),
+                (".md".to_string(), 2, 5),
+                (".rs".to_string(), 2, 15)
+            ]
+        );
+        assert_eq!(
+            summary("lang"),
+            vec![
+                ("(other)".to_string(), 1, 1),
+                ("Markdown".to_string(), 2, 5),
+                ("Rust".to_string(), 2, 15)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_directory_and_depth() {
+        assert_eq!(
+            summary("dir"),
+            vec![
+                (".".to_string(), 2, 4),
+                ("src".to_string(), 1, 10),
+                ("src/cli".to_string(), 2, 7)
+            ]
+        );
+        assert_eq!(
+            summary("depth:1"),
+            vec![(".".to_string(), 2, 4), ("src".to_string(), 3, 17)]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// Every profile is built from a short bundled sample of everyday prose in its
+// language.
+const SAMPLES: [(&str, &str); 16] = [
+    ("cs", include_str!("profiles/cs.txt")),
+    ("de", include_str!("profiles/de.txt")),
+    ("el", include_str!("profiles/el.txt")),
+    ("en", include_str!("profiles/en.txt")),
+    ("es", include_str!("profiles/es.txt")),
+    ("fi", include_str!("profiles/fi.txt")),
+    ("fr", include_str!("profiles/fr.txt")),
+    ("hu", include_str!("profiles/hu.txt")),
+    ("id", include_str!("profiles/id.txt")),
+    ("it", include_str!("profiles/it.txt")),
+    ("nl", include_str!("profiles/nl.txt")),
+    ("pl", include_str!("profiles/pl.txt")),
+    ("pt", include_str!("profiles/pt.txt")),
+    ("ru", include_str!("profiles/ru.txt")),
+    ("sv", include_str!("profiles/sv.txt")),
+    ("tr", include_str!("profiles/tr.txt")),
+];
+
+const PROFILE_SIZE: usize = 300;
+const MIN_TRIGRAMS: usize = 30;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Language {
+    pub code: &'static str,
+    pub confidence: f64,
+}
+
+#[derive(Default)]
+pub struct Trigrams {
+    counts: HashMap<String, usize>,
+    total: usize,
+}
+
+impl Trigrams {
+    // Words are lowercased runs of letters padded with a space on each side,
+    // so trigrams also capture how words start and end.
+    pub fn add_line(&mut self, line: &str) {
+        let words = line
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|word| !word.is_empty());
+        for word in words {
+            let padded: Vec<char> = std::iter::once(' ')
+                .chain(word.chars().flat_map(char::to_lowercase))
+                .chain([' '])
+                .collect();
+            for trigram in padded.windows(3) {
+                *self.counts.entry(trigram.iter().collect()).or_insert(0) += 1;
+                self.total += 1;
+            }
+        }
+    }
+
+    // Ranks the most frequent trigrams, ties in alphabetical order.
+    fn profile(&self) -> HashMap<&str, usize> {
+        let mut trigrams: Vec<(&str, usize)> = self
+            .counts
+            .iter()
+            .map(|(trigram, &count)| (trigram.as_str(), count))
+            .collect();
+        trigrams.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+        trigrams
+            .into_iter()
+            .take(PROFILE_SIZE)
+            .enumerate()
+            .map(|(rank, (trigram, _))| (trigram, rank))
+            .collect()
+    }
+
+    // Cavnar and Trenkle's out-of-place distance picks the closest profile.
+    // Confidence is how much closer it is than the runner-up, from 0 for a
+    // tie to 1. Too little text, a tie, or text sharing no trigram with the
+    // closest profile, as in a script no profile covers, is reported as
+    // undetermined.
+    pub fn detect(&self) -> Language {
+        let undetermined = Language {
+            code: "und",
+            confidence: 0.0,
+        };
+        if self.total < MIN_TRIGRAMS {
+            return undetermined;
+        }
+
+        let document = self.profile();
+        let mut distances: Vec<(usize, &'static str, &Profile)> = profiles()
+            .iter()
+            .map(|(code, profile)| {
+                let distance = document
+                    .iter()
+                    .map(|(trigram, rank)| match profile.get(*trigram) {
+                        Some(other) => rank.abs_diff(*other),
+                        None => PROFILE_SIZE,
+                    })
+                    .sum();
+                (distance, *code, profile)
+            })
+            .collect();
+        distances.sort_by_key(|&(distance, code, _)| (distance, code));
+
+        let (best, code, profile) = distances[0];
+        let runner_up = distances[1].0;
+        if best == runner_up
+            || !document
+                .keys()
+                .any(|trigram| profile.contains_key(*trigram))
+        {
+            return undetermined;
+        }
+        Language {
+            code,
+            confidence: 1.0 - best as f64 / runner_up as f64,
+        }
+    }
+}
+
+type Profile = HashMap<String, usize>;
+
+fn profiles() -> &'static [(&'static str, Profile)] {
+    static PROFILES: OnceLock<Vec<(&'static str, Profile)>> = OnceLock::new();
+    PROFILES.get_or_init(|| {
+        SAMPLES
+            .iter()
+            .map(|(code, sample)| {
+                let mut trigrams = Trigrams::default();
+                for line in sample.lines() {
+                    trigrams.add_line(line);
+                }
+                let profile = trigrams
+                    .profile()
+                    .into_iter()
+                    .map(|(trigram, rank)| (trigram.to_string(), rank))
+                    .collect();
+                (*code, profile)
+            })
+            .collect()
+    })
+}
+
+impl Language {
+    pub fn format(&self) -> String {
+        format!("{} {:.2}", self.code, self.confidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn detect(text: &str) -> Language {
+        let mut trigrams = Trigrams::default();
+        for line in text.lines() {
+            trigrams.add_line(line);
+        }
+        trigrams.detect()
+    }
+
+    #[test]
+    fn test_detect_fixture() {
+        let text = fs::read_to_string("input_files/test.txt").expect("failed to read fixture");
+        let language = detect(&text);
+
+        assert_eq!(language.code, "en");
+        assert!(language.confidence > 0.1, "{:?}", language);
+    }
+
+    #[test]
+    fn test_detect_short_passages() {
+        let passages = [
+            ("fr", "Il marchait seul dans les rues de la ville, pensant à ceux qu'il avait aimés et qui ne reviendraient jamais."),
+            ("de", "Gestern Abend sind wir noch lange am Fluss spazieren gegangen, weil die Luft so angenehm war."),
+            ("es", "Mañana por la tarde iremos al mercado para comprar fruta fresca y pan para toda la semana."),
+            ("it", "Quando siamo arrivati alla stazione il treno era già partito e abbiamo dovuto aspettare un'ora."),
+            ("pl", "Wczoraj wieczorem długo spacerowaliśmy nad rzeką, bo powietrze było bardzo przyjemne."),
+            ("ru", "Вчера вечером мы долго гуляли у реки, потому что погода была очень хорошая."),
+        ];
+
+        for (code, passage) in passages {
+            assert_eq!(detect(passage).code, code, "{}", passage);
+        }
+    }
+
+    #[test]
+    fn test_too_little_text() {
+        assert_eq!(detect("").code, "und");
+        assert_eq!(detect("Hello 123").code, "und");
+        assert_eq!(
+            detect("吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。何でも薄暗いじめじめした所でニャーニャー泣いていた事だけは記憶している。"),
+            Language {
+                code: "und",
+                confidence: 0.0
+            }
+        );
+        assert_eq!(
+            Language {
+                code: "fr",
+                confidence: 0.4567
+            }
+            .format(),
+            "fr 0.46"
+        );
+    }
+}
@@ -1,12 +1,170 @@
-use crate::wc::get_statistics;
+use crate::check::{check_paths, Rules};
+use crate::cli::Command;
+use crate::config::{filter_paths, parse_args, show_config};
+use crate::git::collect_rev_paths;
+use crate::group::group;
+use crate::per_line::for_each_line;
+use crate::progress::Progress;
+use crate::report::{
+    get_file_report, get_result, is_per_file, print_error, print_groups, print_line_stats,
+    print_results, print_violations, render_report,
+};
+use crate::select::{enable_selected_counts, select};
+use crate::serve::{client, serve};
+use crate::template::enable_counts;
+use crate::walk::collect_paths;
+use crate::wc::ResultWc;
 
+mod check;
+mod classes;
 mod cli;
+mod config;
+mod csv;
+mod decompress;
+mod dups;
+mod estimate;
+mod git;
+mod group;
+mod lang;
+mod ngrams;
+mod numbers;
+mod per_line;
+mod pool;
+mod progress;
+mod prose;
+mod range;
+mod report;
+mod select;
+mod serve;
+mod special;
+mod split;
+mod template;
+mod tokens;
+mod walk;
 mod wc;
 
-fn print_result(result: wc::ResultWc) {
-    print!("{}", result.format());
-}
-
 fn main() {
-    print_result(get_statistics());
+    let (mut args, loaded) = match parse_args() {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    enable_counts(&mut args);
+    enable_selected_counts(&mut args);
+    if args.show_config {
+        print!("{}", show_config(&args, &loaded));
+        return;
+    }
+    if let Some(Command::Client { socket, args }) = &args.command {
+        match client(socket, args) {
+            Ok(response) => print!("{}", response),
+            Err(err) => eprintln!("{}: {}", socket.display(), err),
+        }
+        return;
+    }
+    if let Some(socket) = &args.serve {
+        if let Err(err) = serve(socket) {
+            eprintln!("{}: {}", socket.display(), err);
+        }
+        return;
+    }
+
+    let paths = match &args.rev {
+        Some(rev) => collect_rev_paths(&args.paths, rev, args.recursive),
+        None => collect_paths(&args.paths, args.recursive),
+    };
+    let paths = match filter_paths(paths, &args) {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let progress =
+        if args.progress && !args.estimate && args.rev.is_none() && args.per_line.is_none() {
+            Progress::start(&paths)
+        } else {
+            None
+        };
+
+    if let Some(rules) = &args.check {
+        let rules = match Rules::load(rules) {
+            Ok(rules) => rules,
+            Err(err) => {
+                eprintln!("{}: {}", rules.display(), err);
+                std::process::exit(2);
+            }
+        };
+        let (violations, errors) = check_paths(&paths, &rules, &args);
+        print_violations(&violations, errors, &args);
+        if !violations.is_empty() || errors > 0 {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.per_line.is_some() {
+        for path in &paths {
+            if let Err(err) = for_each_line(path, &args, |stats| print_line_stats(&stats, &args)) {
+                print_error(&path.display().to_string(), &err, &args);
+            }
+        }
+        return;
+    }
+
+    if is_per_file(&args) {
+        pool::each(
+            &paths,
+            args.jobs as usize,
+            |path| {
+                get_file_report(path, &args, progress.as_ref())
+                    .map(|report| render_report(&report, report.format(), &args))
+            },
+            |path, output| {
+                if let Some(progress) = &progress {
+                    progress.clear();
+                }
+                match output {
+                    Ok(output) => print!("{}", output),
+                    Err(err) => print_error(&path.display().to_string(), &err, &args),
+                }
+            },
+        );
+        return;
+    }
+
+    let outcomes = pool::run(&paths, args.jobs as usize, |path| {
+        get_result(path, &args, progress.as_ref())
+    });
+    if let Some(progress) = &progress {
+        progress.clear();
+    }
+    let mut results = Vec::new();
+    let mut failed = false;
+    for (path, outcome) in paths.iter().zip(outcomes) {
+        match outcome {
+            Ok(result) => results.push(result),
+            Err(err) => {
+                print_error(&path.display().to_string(), &err, &args);
+                failed = true;
+            }
+        }
+    }
+
+    let mut total = ResultWc::total();
+    for result in &results {
+        total.add(result);
+    }
+    match &args.group_by {
+        Some(by) => print_groups(&group(&results, by), &total, &args),
+        None => {
+            let total = (paths.len() > 1).then_some(&total);
+            print_results(&select(results, &args), total, &args);
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
 }
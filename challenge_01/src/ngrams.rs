@@ -0,0 +1,188 @@
+use crate::cli::{Cli, NgramUnit};
+use crate::progress::Progress;
+use crate::split::{decode, terminated_records, words};
+use crate::wc::{display_name, open_input};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct NgramStats {
+    n: usize,
+    unit: NgramUnit,
+    total: usize,
+    distinct: usize,
+    entropy: f64,
+    top: Vec<(usize, String)>,
+    #[serde(rename = "name")]
+    file_name: String,
+}
+
+pub fn get_ngram_statistics(
+    path: &Path,
+    args: &Cli,
+    n: usize,
+    progress: Option<&Arc<Progress>>,
+) -> Result<NgramStats, String> {
+    let reader = open_input(path, args, progress)?;
+
+    count_ngrams(reader, args, n, display_name(path, args))
+}
+
+// The window slides over the whole input, so n-grams span record boundaries.
+// Character n-grams see each record end with the separator that ended it,
+// if any.
+fn count_ngrams<R: BufRead>(
+    reader: R,
+    args: &Cli,
+    n: usize,
+    file_name: String,
+) -> Result<NgramStats, String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut window: VecDeque<String> = VecDeque::with_capacity(n);
+    let separator = match args.unit {
+        NgramUnit::Char => "",
+        NgramUnit::Word => " ",
+    };
+    let mut push = |unit: String| {
+        if window.len() == n {
+            window.pop_front();
+        }
+        window.push_back(unit);
+        if window.len() == n {
+            *counts
+                .entry(
+                    window
+                        .iter()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(separator),
+                )
+                .or_insert(0) += 1;
+        }
+    };
+
+    for record in terminated_records(reader, args.record_sep.as_ref()) {
+        let (record, end) = record
+            .and_then(|(record, end)| Ok((decode(record, &args.encoding)?, end)))
+            .map_err(|_| "Error at reading the file".to_string())?;
+        match args.unit {
+            NgramUnit::Char => {
+                for c in record.chars().chain(String::from_utf8_lossy(&end).chars()) {
+                    push(c.to_string());
+                }
+            }
+            NgramUnit::Word => {
+                for word in words(args.word_delim.as_ref(), &record) {
+                    push(word.to_string());
+                }
+            }
+        }
+    }
+
+    let total: usize = counts.values().sum();
+    let entropy = counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum::<f64>()
+        .max(0.0);
+    let distinct = counts.len();
+    let mut top: Vec<(usize, String)> = counts
+        .into_iter()
+        .map(|(gram, count)| (count, gram))
+        .collect();
+    top.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    top.truncate(args.ngrams_top);
+
+    Ok(NgramStats {
+        n,
+        unit: args.unit.clone(),
+        total,
+        distinct,
+        entropy,
+        top,
+        file_name,
+    })
+}
+
+impl NgramStats {
+    pub fn format(&self) -> String {
+        let unit = match self.unit {
+            NgramUnit::Char => "char",
+            NgramUnit::Word => "word",
+        };
+        let mut result = format!(
+            "{} {} {}-grams {} distinct {:.3} bits entropy {}\n",
+            self.total, unit, self.n, self.distinct, self.entropy, self.file_name
+        );
+        for (count, gram) in &self.top {
+            result.push_str(&format!(
+                "{} {:.2}% {}\n",
+                count,
+                *count as f64 / self.total as f64 * 100.0,
+                gram.escape_debug()
+            ));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn ngrams(text: &str, options: &[&str]) -> NgramStats {
+        let args = Cli::parse_from(["wc"].iter().chain(options).chain(&["unused"]));
+        let n = args.ngrams.expect("missing --ngrams") as usize;
+        count_ngrams(text.as_bytes(), &args, n, "text".to_string())
+            .expect("failed to count n-grams")
+    }
+
+    #[test]
+    fn test_char_bigrams() {
+        let stats = ngrams("abab\na", &["--ngrams=2", "--ngrams-top=2"]);
+
+        assert_eq!(
+            stats.format(),
+            "5 char 2-grams 4 distinct 1.922 bits entropy text\n\
+             2 40.00% ab\n\
+             1 20.00% \\na\n"
+        );
+    }
+
+    #[test]
+    fn test_word_trigrams_across_lines() {
+        let stats = ngrams("to be or\nnot to be", &["--ngrams=3", "--unit=word"]);
+
+        assert_eq!(stats.total, 4);
+        assert_eq!(stats.top[0], (1, "be or not".to_string()));
+    }
+
+    #[test]
+    fn test_character_frequencies() {
+        let uniform = ngrams("abcd", &["--ngrams=1"]);
+        let terminated = ngrams("abcd\n", &["--ngrams=1"]);
+        let separated = ngrams("aaxax", &["--ngrams=1", "--record-sep=x"]);
+        let unterminated = ngrams("abab", &["--ngrams=1"]);
+
+        assert_eq!(uniform.distinct, 4);
+        assert!((uniform.entropy - 2.0).abs() < 1e-9);
+        assert_eq!(terminated.distinct, 5);
+        assert!((terminated.entropy - 5f64.log2()).abs() < 1e-9);
+        assert_eq!(
+            separated.top,
+            vec![(3, "a".to_string()), (2, "x".to_string())]
+        );
+        assert_eq!(
+            unterminated.top,
+            vec![(2, "a".to_string()), (2, "b".to_string())]
+        );
+    }
+}
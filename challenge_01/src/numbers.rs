@@ -0,0 +1,128 @@
+use crate::cli::{Cli, Units};
+use std::env;
+
+pub enum NumberStyle {
+    Plain,
+    Human(Units),
+    Thousands(char),
+}
+
+impl NumberStyle {
+    pub fn from_args(args: &Cli) -> Self {
+        match (&args.human, args.thousands) {
+            (Some(units), _) => NumberStyle::Human(units.clone()),
+            (None, true) => NumberStyle::Thousands(locale_separator()),
+            (None, false) => NumberStyle::Plain,
+        }
+    }
+
+    pub fn count(&self, value: usize) -> String {
+        match self {
+            NumberStyle::Plain => value.to_string(),
+            NumberStyle::Human(_) => scale(value, 1000.0, &["", "K", "M", "G", "T", "P"]),
+            NumberStyle::Thousands(separator) => group(value, *separator),
+        }
+    }
+
+    pub fn bytes(&self, value: usize) -> String {
+        match self {
+            NumberStyle::Human(Units::Si) => {
+                scale(value, 1000.0, &["B", "kB", "MB", "GB", "TB", "PB"])
+            }
+            NumberStyle::Human(Units::Iec) => {
+                scale(value, 1024.0, &["B", "KiB", "MiB", "GiB", "TiB", "PiB"])
+            }
+            _ => self.count(value),
+        }
+    }
+}
+
+// Like `ls -h`: one decimal below 10, whole numbers above.
+fn scale(value: usize, base: f64, suffixes: &[&str]) -> String {
+    let mut scaled = value as f64;
+    let mut suffix = 0;
+    while scaled >= base && suffix < suffixes.len() - 1 {
+        scaled /= base;
+        suffix += 1;
+    }
+    // 999500 and up would round to 1000K, which is 1.0M.
+    if suffix > 0 && suffix < suffixes.len() - 1 && scaled.round() >= base {
+        scaled /= base;
+        suffix += 1;
+    }
+
+    if suffix == 0 {
+        format!("{}{}", value, suffixes[0])
+    } else if scaled < 9.95 {
+        format!("{:.1}{}", scaled, suffixes[suffix])
+    } else {
+        format!("{:.0}{}", scaled, suffixes[suffix])
+    }
+}
+
+fn group(value: usize, separator: char) -> String {
+    let digits = value.to_string();
+    let mut result = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            result.push(separator);
+        }
+        result.push(digit);
+    }
+
+    result
+}
+
+fn locale_separator() -> char {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+
+    separator_for(&locale)
+}
+
+fn separator_for(locale: &str) -> char {
+    let language = locale.split(['_', '.', '@', '-']).next().unwrap_or("");
+    match language {
+        "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro" => '.',
+        "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "uk" | "hu" | "bg" => ' ',
+        _ => ',',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human() {
+        let si = NumberStyle::Human(Units::Si);
+        let iec = NumberStyle::Human(Units::Iec);
+
+        assert_eq!(si.count(999), "999");
+        assert_eq!(si.count(3369045), "3.4M");
+        assert_eq!(si.count(58164), "58K");
+        assert_eq!(si.bytes(325002), "325kB");
+        assert_eq!(iec.bytes(325002), "317KiB");
+        assert_eq!(iec.bytes(1536), "1.5KiB");
+        assert_eq!(iec.bytes(512), "512B");
+        assert_eq!(iec.count(1536), "1.5K");
+        assert_eq!(si.count(999_499), "999K");
+        assert_eq!(si.count(999_950), "1.0M");
+        assert_eq!(si.bytes(999_999_999), "1.0GB");
+        assert_eq!(iec.bytes(1_048_575), "1.0MiB");
+    }
+
+    #[test]
+    fn test_thousands() {
+        assert_eq!(NumberStyle::Thousands(',').count(3369045), "3,369,045");
+        assert_eq!(NumberStyle::Thousands('.').bytes(100000), "100.000");
+        assert_eq!(NumberStyle::Thousands(',').count(999), "999");
+        assert_eq!(separator_for("de_DE.UTF-8"), '.');
+        assert_eq!(separator_for("fr_FR"), ' ');
+        assert_eq!(separator_for("en_US.UTF-8"), ',');
+        assert_eq!(separator_for("C"), ',');
+    }
+}
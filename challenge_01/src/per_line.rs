@@ -0,0 +1,140 @@
+use crate::cli::Cli;
+use crate::range::open_range;
+use crate::split::{decode, records, words};
+use crate::wc::{display_name, open_input};
+use serde::Serialize;
+use std::io::BufRead;
+use std::path::Path;
+use unicode_width::UnicodeWidthChar;
+
+const TAB_STOP: usize = 8;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LineStats {
+    pub name: String,
+    pub line: u64,
+    pub bytes: usize,
+    pub chars: usize,
+    pub words: usize,
+    pub width: usize,
+}
+
+impl LineStats {
+    pub fn format(&self) -> String {
+        format!(
+            "{}:{} {} {} {} {}",
+            self.name, self.line, self.bytes, self.chars, self.words, self.width
+        )
+    }
+}
+
+// Tabs move to the next tab stop as in a terminal, wide characters take two
+// columns and control characters none.
+pub fn display_width(line: &str) -> usize {
+    line.chars().fold(0, |width, c| match c {
+        '\t' => width + TAB_STOP - width % TAB_STOP,
+        c => width + c.width().unwrap_or(0),
+    })
+}
+
+// Hands every line to `emit` as soon as it is read, or only the lines wider
+// than the --per-line threshold. With --lines-range the numbering starts at
+// the first line of the range. Lines go out as they are read, so there is no
+// progress bar to draw over them.
+pub fn for_each_line(
+    path: &Path,
+    args: &Cli,
+    mut emit: impl FnMut(LineStats),
+) -> Result<(), String> {
+    let threshold = args.per_line.flatten();
+    let first = args
+        .lines_range
+        .as_ref()
+        .map_or(1, |range| range.start.max(1));
+    let reader: Box<dyn BufRead> = match open_range(path, args, None)? {
+        Some(window) => Box::new(window),
+        None => open_input(path, args, None)?,
+    };
+    let name = display_name(path, args);
+
+    for (line, record) in (first..).zip(records(reader, args.record_sep.as_ref())) {
+        let record = record.map_err(|_| "Error at reading the file".to_string())?;
+        let bytes = record.len();
+        let text = decode(record, &args.encoding)
+            .map_err(|_| format!("Invalid UTF-8 on line {}", line))?;
+        let width = display_width(&text);
+        if threshold.is_some_and(|threshold| width <= threshold) {
+            continue;
+        }
+
+        emit(LineStats {
+            name: name.clone(),
+            line,
+            bytes,
+            chars: text.chars().count(),
+            words: words(args.word_delim.as_ref(), &text).len(),
+            width,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wc::file_name;
+    use clap::Parser;
+    use std::fs;
+
+    fn per_line(content: &str, options: &[&str]) -> Vec<String> {
+        let path = std::env::temp_dir().join(format!(
+            "wc_per_line_{}_{}.txt",
+            std::process::id(),
+            options.join("")
+        ));
+        fs::write(&path, content).expect("failed to create test file");
+        let path_str = path.to_str().unwrap();
+        let name = file_name(&path);
+        let args = Cli::parse_from(["wc"].iter().chain(options).chain(&[path_str]));
+
+        let mut lines = Vec::new();
+        for_each_line(&path, &args, |stats| {
+            lines.push(stats.format().replace(&name, "f"))
+        })
+        .expect("failed to read lines");
+        fs::remove_file(&path).expect("error when removing test file");
+        lines
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("a\tb"), 9);
+        assert_eq!(display_width("日本"), 4);
+        assert_eq!(display_width("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn test_per_line() {
+        let content = "one two\n\ncafé\tau lait\n日本語\n";
+
+        assert_eq!(
+            per_line(content, &["--per-line"]),
+            [
+                "f:1 7 7 2 7",
+                "f:2 0 0 0 0",
+                "f:3 13 12 3 15",
+                "f:4 9 3 1 6"
+            ]
+        );
+        assert_eq!(
+            per_line(content, &["--per-line=6"]),
+            ["f:1 7 7 2 7", "f:3 13 12 3 15"]
+        );
+        assert_eq!(
+            per_line(content, &["--per-line", "--lines-range=3:"]),
+            ["f:3 13 12 3 15", "f:4 9 3 1 6"]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+// Every worker starts with its own contiguous share of the files, takes them
+// from the front of its queue and, once that runs dry, steals from the back
+// of the longest other queue. A worker has a single file open at a time, so
+// there are never more than `jobs` open at once. Results go to `emit` in the
+// order of `paths`, each one as soon as all the paths before it are done. A
+// panic while working on a path becomes an error for that path.
+pub fn each<T: Send>(
+    paths: &[PathBuf],
+    jobs: usize,
+    work: impl Fn(&Path) -> Result<T, String> + Sync,
+    mut emit: impl FnMut(&Path, Result<T, String>),
+) {
+    let work = |path: &Path| {
+        panic::catch_unwind(AssertUnwindSafe(|| work(path)))
+            .unwrap_or_else(|_| Err("worker panicked".to_string()))
+    };
+    let jobs = jobs.clamp(1, paths.len().max(1));
+    if jobs == 1 {
+        for path in paths {
+            emit(path, work(path));
+        }
+        return;
+    }
+
+    let share = paths.len().div_ceil(jobs);
+    let queues: Vec<Mutex<VecDeque<usize>>> = (0..jobs)
+        .map(|worker| Mutex::new((worker * share..paths.len().min((worker + 1) * share)).collect()))
+        .collect();
+
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for worker in 0..jobs {
+            let (queues, work, sender) = (&queues, &work, sender.clone());
+            scope.spawn(move || {
+                while let Some(index) = next(queues, worker) {
+                    // The receiver lives until every worker is done.
+                    let _ = sender.send((index, work(&paths[index])));
+                }
+            });
+        }
+        drop(sender);
+
+        let mut waiting = BTreeMap::new();
+        let mut first = 0;
+        for (index, result) in receiver {
+            waiting.insert(index, result);
+            while let Some(result) = waiting.remove(&first) {
+                emit(&paths[first], result);
+                first += 1;
+            }
+        }
+    });
+}
+
+pub fn run<T: Send>(
+    paths: &[PathBuf],
+    jobs: usize,
+    work: impl Fn(&Path) -> Result<T, String> + Sync,
+) -> Vec<Result<T, String>> {
+    let mut results = Vec::with_capacity(paths.len());
+    each(paths, jobs, work, |_, result| results.push(result));
+    results
+}
+
+fn next(queues: &[Mutex<VecDeque<usize>>], worker: usize) -> Option<usize> {
+    if let Some(index) = queues[worker].lock().unwrap().pop_front() {
+        return Some(index);
+    }
+
+    // Queues only shrink, so this ends once all of them are empty.
+    loop {
+        let (victim, len) = queues
+            .iter()
+            .enumerate()
+            .filter(|&(other, _)| other != worker)
+            .map(|(other, queue)| (other, queue.lock().unwrap().len()))
+            .max_by_key(|&(_, len)| len)?;
+        if len == 0 {
+            return None;
+        }
+        if let Some(index) = queues[victim].lock().unwrap().pop_back() {
+            return Some(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn paths(count: usize) -> Vec<PathBuf> {
+        (0..count)
+            .map(|index| PathBuf::from(index.to_string()))
+            .collect()
+    }
+
+    fn index(path: &Path) -> usize {
+        path.to_str().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn test_results_keep_path_order() {
+        let paths = paths(40);
+        let results = run(&paths, 4, |path| {
+            let index = index(path);
+            // The first share is slow, so the other workers steal from it.
+            if index < 10 {
+                thread::sleep(Duration::from_millis(5));
+            }
+            if index.is_multiple_of(7) {
+                Err(format!("{} failed", index))
+            } else {
+                Ok(index)
+            }
+        });
+
+        assert_eq!(results.len(), 40);
+        for (index, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(value) => assert_eq!(value, index),
+                Err(err) => assert_eq!(err, format!("{} failed", index)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_jobs_bound_concurrency() {
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+        let paths = paths(30);
+
+        run(&paths, 3, |_| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(2));
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        });
+
+        assert!((2..=3).contains(&most.load(Ordering::SeqCst)));
+        assert_eq!(run(&[], 8, |_| Ok(0)), Vec::new());
+    }
+
+    #[test]
+    fn test_results_stream_in_order() {
+        let paths = paths(20);
+        let emitted = Mutex::new(Vec::new());
+        let results = Mutex::new(Vec::new());
+        each(
+            &paths,
+            4,
+            |path| {
+                let index = index(path);
+                // The first path is slow: nothing may come out before it,
+                // and it comes out before the last ones are done.
+                if index == 0 {
+                    thread::sleep(Duration::from_millis(20));
+                } else if index >= 15 {
+                    while emitted.lock().unwrap().is_empty() {
+                        thread::sleep(Duration::from_millis(1));
+                    }
+                }
+                Ok(index)
+            },
+            |path, result| {
+                emitted.lock().unwrap().push(index(path));
+                results.lock().unwrap().push(result);
+            },
+        );
+
+        assert_eq!(emitted.into_inner().unwrap(), (0..20).collect::<Vec<_>>());
+        assert_eq!(
+            results.into_inner().unwrap(),
+            (0..20).map(Ok).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_panic_is_an_error_for_its_path() {
+        let paths = paths(8);
+        for jobs in [1, 3] {
+            let results = run(&paths, jobs, |path| {
+                if index(path) == 5 {
+                    panic!("bad file");
+                }
+                Ok(index(path))
+            });
+
+            assert_eq!(results[5], Err("worker panicked".to_string()));
+            assert_eq!(results[4], Ok(4));
+            assert_eq!(results[7], Ok(7));
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Progress {
+    total: Option<u64>,
+    done: AtomicU64,
+    started: Instant,
+    last_draw: Mutex<Option<Instant>>,
+}
+
+pub struct ProgressReader<R: Read> {
+    inner: R,
+    progress: Arc<Progress>,
+}
+
+impl Progress {
+    // The indicator is only shown on an interactive stderr. Any input whose
+    // size is not known up front (pipes, /proc files) leaves the total unknown.
+    pub fn start(paths: &[PathBuf]) -> Option<Arc<Progress>> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+
+        Some(Arc::new(Progress::new(known_size(paths))))
+    }
+
+    fn new(total: Option<u64>) -> Self {
+        Progress {
+            total,
+            done: AtomicU64::new(0),
+            started: Instant::now(),
+            last_draw: Mutex::new(None),
+        }
+    }
+
+    fn advance(&self, bytes: u64) {
+        self.done.fetch_add(bytes, Ordering::Relaxed);
+
+        let mut last_draw = self.last_draw.lock().unwrap();
+        let now = Instant::now();
+        if last_draw.is_some_and(|last| now.duration_since(last) < REDRAW_INTERVAL) {
+            return;
+        }
+        *last_draw = Some(now);
+        eprint!("\r{}\x1b[K", self.status(now));
+        let _ = io::stderr().flush();
+    }
+
+    // Wipes the indicator so regular output is not printed over it.
+    pub fn clear(&self) {
+        if self.last_draw.lock().unwrap().take().is_some() {
+            eprint!("\r\x1b[K");
+        }
+    }
+
+    fn status(&self, now: Instant) -> String {
+        let done = self.done.load(Ordering::Relaxed);
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            done as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let mut status = format_bytes(done as f64);
+        if let Some(total) = self.total.filter(|&total| total > 0) {
+            let percent = (done as f64 / total as f64 * 100.0).min(100.0);
+            status.push_str(&format!(
+                " / {} ({:.1}%)",
+                format_bytes(total as f64),
+                percent
+            ));
+        }
+        status.push_str(&format!(" {}/s", format_bytes(rate)));
+        if let Some(total) = self.total {
+            if rate > 0.0 && total >= done {
+                status.push_str(&format!(" ETA {}s", ((total - done) as f64 / rate).ceil()));
+            }
+        }
+
+        status
+    }
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, progress: Arc<Progress>) -> Self {
+        ProgressReader { inner, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.advance(read as u64);
+        Ok(read)
+    }
+}
+
+fn known_size(paths: &[PathBuf]) -> Option<u64> {
+    paths.iter().try_fold(0, |total, path| {
+        let metadata = path.metadata().ok()?;
+        if metadata.is_file() && metadata.len() > 0 {
+            Some(total + metadata.len())
+        } else {
+            None
+        }
+    })
+}
+
+fn format_bytes(bytes: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, units[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_reader_counts_bytes() {
+        let progress = Arc::new(Progress::new(Some(2048)));
+        let data = "a".repeat(1024);
+        let mut reader = ProgressReader::new(data.as_bytes(), progress.clone());
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).expect("failed to read");
+
+        assert_eq!(progress.done.load(Ordering::Relaxed), 1024);
+        assert!(progress
+            .status(Instant::now())
+            .starts_with("1.0 KiB / 2.0 KiB (50.0%)"));
+    }
+
+    #[test]
+    fn test_progress_unknown_total() {
+        let progress = Progress::new(None);
+        progress.done.store(3 * 1024 * 1024, Ordering::Relaxed);
+
+        let status = progress.status(Instant::now());
+        assert!(status.starts_with("3.0 MiB "));
+        assert!(!status.contains('%'));
+        assert!(!status.contains("ETA"));
+    }
+}
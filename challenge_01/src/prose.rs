@@ -0,0 +1,709 @@
+use crate::cli::ProseFormat;
+use std::path::Path;
+
+const HTML_SKIPPED: [&str; 5] = ["script", "style", "pre", "code", "template"];
+const HTML_INLINE: [&str; 14] = [
+    "a", "abbr", "b", "cite", "em", "i", "mark", "q", "s", "small", "span", "strong", "sub", "sup",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Markup {
+    Plain,
+    Markdown,
+    Html,
+    AsciiDoc,
+}
+
+enum AsciiDocBlock {
+    Code,
+    Comment,
+    Passthrough,
+}
+
+enum HtmlState {
+    Text,
+    Tag(String),
+    Comment,
+}
+
+// Turns markup into the text a reader would see, one input line at a time.
+// `line` returns None for lines that carry no prose at all.
+pub struct Prose {
+    markup: Markup,
+    pub code_lines: usize,
+    line_number: usize,
+    front_matter: Option<&'static str>,
+    fence: Option<(char, usize)>,
+    in_paragraph: bool,
+    in_list: bool,
+    block: Option<(String, AsciiDocBlock)>,
+    source_paragraph: bool,
+    html_state: HtmlState,
+    html_skip: Option<String>,
+}
+
+impl Markup {
+    pub fn for_path(path: &Path, format: &ProseFormat) -> Markup {
+        match format {
+            ProseFormat::Markdown => return Markup::Markdown,
+            ProseFormat::Html => return Markup::Html,
+            ProseFormat::Asciidoc => return Markup::AsciiDoc,
+            ProseFormat::Auto => {}
+        }
+
+        let mut path = path.to_path_buf();
+        while path
+            .extension()
+            .is_some_and(|ext| ext == "gz" || ext == "z" || ext == "zz" || ext == "deflate")
+        {
+            path.set_extension("");
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        match extension.as_deref() {
+            Some("md" | "markdown" | "mdown" | "mkd") => Markup::Markdown,
+            Some("html" | "htm" | "xhtml") => Markup::Html,
+            Some("adoc" | "asciidoc" | "asc") => Markup::AsciiDoc,
+            _ => Markup::Plain,
+        }
+    }
+}
+
+impl Prose {
+    pub fn new(markup: Markup) -> Self {
+        Prose {
+            markup,
+            code_lines: 0,
+            line_number: 0,
+            front_matter: None,
+            fence: None,
+            in_paragraph: false,
+            in_list: false,
+            block: None,
+            source_paragraph: false,
+            html_state: HtmlState::Text,
+            html_skip: None,
+        }
+    }
+
+    pub fn line(&mut self, line: &str) -> Option<String> {
+        self.line_number += 1;
+        let text = match self.markup {
+            Markup::Plain => return Some(line.to_string()),
+            Markup::Markdown => self.markdown_line(line)?,
+            Markup::Html => self.html_line(line)?,
+            Markup::AsciiDoc => self.asciidoc_line(line)?,
+        };
+
+        if text.trim().is_empty() && !line.trim().is_empty() {
+            None
+        } else {
+            Some(text)
+        }
+    }
+
+    fn markdown_line(&mut self, line: &str) -> Option<String> {
+        let trimmed = line.trim();
+
+        if self.line_number == 1 && (trimmed == "---" || trimmed == "+++") {
+            self.front_matter = Some(if trimmed == "---" { "---" } else { "+++" });
+            return None;
+        }
+        if let Some(delimiter) = self.front_matter {
+            if trimmed == delimiter || (delimiter == "---" && trimmed == "...") {
+                self.front_matter = None;
+            }
+            return None;
+        }
+
+        let indent = indentation(line);
+        if let Some((fence_char, fence_len)) = self.fence {
+            let closing = trimmed.chars().take_while(|&c| c == fence_char).count();
+            if indent < 4 && closing >= fence_len && closing == trimmed.len() {
+                self.fence = None;
+            } else {
+                self.code_lines += 1;
+            }
+            return None;
+        }
+        if indent < 4 {
+            if let Some(fence_char) = trimmed.chars().next().filter(|&c| c == '`' || c == '~') {
+                let fence_len = trimmed.chars().take_while(|&c| c == fence_char).count();
+                if fence_len >= 3 {
+                    self.fence = Some((fence_char, fence_len));
+                    self.in_paragraph = false;
+                    return None;
+                }
+            }
+        }
+
+        if trimmed.is_empty() {
+            self.in_paragraph = false;
+            return Some(String::new());
+        }
+        if indent >= 4 && !self.in_paragraph && !self.in_list {
+            self.code_lines += 1;
+            return None;
+        }
+        if indent < 4 && (is_reference_definition(trimmed) || is_markdown_rule(trimmed)) {
+            self.in_paragraph = false;
+            return None;
+        }
+
+        let mut text = trimmed;
+        while let Some(rest) = text.strip_prefix('>') {
+            text = rest.trim_start();
+        }
+        let heading = text.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&heading)
+            && text[heading..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+        {
+            text = text[heading..].trim().trim_end_matches('#').trim_end();
+        }
+        let list_item = strip_list_marker(text);
+        self.in_list = list_item.is_some() || (self.in_list && indent > 0);
+        if let Some(item) = list_item {
+            text = item;
+            for task in ["[ ] ", "[x] ", "[X] "] {
+                text = text.strip_prefix(task).unwrap_or(text);
+            }
+        }
+        self.in_paragraph = true;
+
+        let text = if text.starts_with('|') {
+            text.replace('|', " ")
+        } else {
+            text.to_string()
+        };
+        Some(decode_entities(&markdown_inline(&text)))
+    }
+
+    fn html_line(&mut self, line: &str) -> Option<String> {
+        let mut text = String::new();
+        let mut skipped_text = false;
+        let mut chars = line.char_indices().peekable();
+
+        while let Some((index, c)) = chars.next() {
+            match &mut self.html_state {
+                HtmlState::Comment => {
+                    if c == '>' && line[..index].ends_with("--") {
+                        self.html_state = HtmlState::Text;
+                    }
+                }
+                HtmlState::Tag(tag) => {
+                    if c == '>' {
+                        let tag = std::mem::take(tag);
+                        self.html_state = HtmlState::Text;
+                        self.close_html_tag(&tag, &mut text);
+                    } else {
+                        tag.push(c);
+                    }
+                }
+                HtmlState::Text => {
+                    let next = chars.peek().map(|&(_, next)| next);
+                    if c == '<' && line[index..].starts_with("<!--") {
+                        self.html_state = HtmlState::Comment;
+                        chars.nth(2);
+                    } else if c == '<'
+                        && next
+                            .is_some_and(|next| next.is_ascii_alphabetic() || "/!?".contains(next))
+                    {
+                        self.html_state = HtmlState::Tag(String::new());
+                    } else if self.html_skip.is_some() {
+                        skipped_text |= !c.is_whitespace();
+                    } else {
+                        text.push(c);
+                    }
+                }
+            }
+        }
+
+        if skipped_text && text.trim().is_empty() {
+            self.code_lines += 1;
+            return None;
+        }
+        Some(decode_entities(&text))
+    }
+
+    fn close_html_tag(&mut self, tag: &str, text: &mut String) {
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+
+        match &self.html_skip {
+            Some(skipped) if closing && *skipped == name => self.html_skip = None,
+            Some(_) => {}
+            None if !closing && !tag.ends_with('/') && HTML_SKIPPED.contains(&name.as_str()) => {
+                self.html_skip = Some(name)
+            }
+            None if !HTML_INLINE.contains(&name.as_str()) => text.push(' '),
+            None => {}
+        }
+    }
+
+    fn asciidoc_line(&mut self, line: &str) -> Option<String> {
+        let trimmed = line.trim_end();
+
+        if let Some((delimiter, kind)) = &self.block {
+            if trimmed == delimiter {
+                self.block = None;
+            } else if matches!(kind, AsciiDocBlock::Code) {
+                self.code_lines += 1;
+            }
+            return None;
+        }
+
+        let delimiter_kind = match trimmed.chars().next() {
+            Some(c) if trimmed.len() >= 4 && trimmed.chars().all(|d| d == c) => match c {
+                '-' | '.' => Some(Some(AsciiDocBlock::Code)),
+                '/' => Some(Some(AsciiDocBlock::Comment)),
+                '+' => Some(Some(AsciiDocBlock::Passthrough)),
+                '=' | '*' | '_' => Some(None),
+                _ => None,
+            },
+            _ if trimmed == "--" => Some(None),
+            _ => None,
+        };
+        if let Some(kind) = delimiter_kind {
+            if let Some(kind) = kind {
+                self.block = Some((trimmed.to_string(), kind));
+            }
+            self.source_paragraph = false;
+            return None;
+        }
+
+        if trimmed.trim().is_empty() {
+            self.in_paragraph = false;
+            self.source_paragraph = false;
+            return Some(String::new());
+        }
+        if self.source_paragraph
+            || (line.starts_with([' ', '\t']) && !self.in_paragraph && !self.in_list)
+        {
+            self.code_lines += 1;
+            return None;
+        }
+
+        let text = trimmed.trim_start();
+        if text.starts_with("//")
+            || is_asciidoc_attribute(text)
+            || text == "|==="
+            || (text.contains("::") && text.ends_with(']') && !text.contains(' '))
+        {
+            return None;
+        }
+        if text.starts_with('[') && text.ends_with(']') {
+            self.source_paragraph = text.starts_with("[source") || text.starts_with("[listing");
+            return None;
+        }
+
+        let mut text = text;
+        let heading = text.chars().take_while(|&c| c == '=').count();
+        if heading > 0 && text[heading..].starts_with(' ') {
+            text = text[heading..].trim();
+        } else if text.starts_with('.') && text[1..].starts_with(|c: char| c.is_alphanumeric()) {
+            text = &text[1..];
+        }
+        let list_item = strip_list_marker(text).or_else(|| {
+            let marker = text.chars().take_while(|&c| c == '*' || c == '.').count();
+            (marker > 0 && text[marker..].starts_with(' ')).then(|| text[marker..].trim_start())
+        });
+        self.in_list = list_item.is_some() || (self.in_list && self.in_paragraph);
+        text = list_item.unwrap_or(text);
+        self.in_paragraph = true;
+
+        let text = text.strip_suffix(" +").unwrap_or(text).replace('|', " ");
+        Some(decode_entities(&asciidoc_inline(&text)))
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn is_reference_definition(line: &str) -> bool {
+    line.starts_with('[')
+        && line
+            .find("]:")
+            .is_some_and(|end| end > 1 && !line[end + 2..].trim().is_empty())
+}
+
+// Thematic breaks, setext heading underlines and table delimiter rows.
+fn is_markdown_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    let rule = compact.len() >= 3
+        && compact
+            .chars()
+            .next()
+            .is_some_and(|first| "-*_=".contains(first) && compact.chars().all(|c| c == first));
+    let table = compact.contains('-') && compact.chars().all(|c| "|:-".contains(c));
+
+    rule || table
+}
+
+fn is_asciidoc_attribute(line: &str) -> bool {
+    line.starts_with(':')
+        && line[1..].find(':').is_some_and(|end| {
+            end > 0
+                && line[1..end + 1]
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "-_!".contains(c))
+        })
+}
+
+fn strip_list_marker(text: &str) -> Option<&str> {
+    if let Some(rest) = text.strip_prefix(['-', '*', '+']) {
+        return rest.starts_with([' ', '\t']).then(|| rest.trim_start());
+    }
+
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let rest = &text[digits..];
+    if (1..=9).contains(&digits)
+        && rest.starts_with(['.', ')'])
+        && rest[1..].starts_with([' ', '\t'])
+    {
+        return Some(rest[1..].trim_start());
+    }
+
+    None
+}
+
+// Finds the closing bracket that matches the opening one at `start`.
+fn matching(text: &[char], start: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (index, &c) in text.iter().enumerate().skip(start) {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(index);
+            }
+        }
+    }
+
+    None
+}
+
+fn markdown_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        match c {
+            '\\' if index + 1 < chars.len() => {
+                result.push(chars[index + 1]);
+                index += 2;
+            }
+            '`' => {
+                let run = chars[index..].iter().take_while(|&&c| c == '`').count();
+                let close = (index + run..chars.len()).find(|&end| {
+                    chars[end..].iter().take_while(|&&c| c == '`').count() == run
+                        && chars[end - 1] != '`'
+                });
+                match close {
+                    Some(end) => index = end + run,
+                    None => {
+                        result.extend(&chars[index..index + run]);
+                        index += run;
+                    }
+                }
+            }
+            '!' if chars.get(index + 1) == Some(&'[') => index += 1,
+            '[' => match matching(&chars, index, '[', ']') {
+                Some(end) => {
+                    let label: String = chars[index + 1..end].iter().collect();
+                    result.push_str(&markdown_inline(&label));
+                    index = end + 1;
+                    match chars.get(index) {
+                        Some('(') => {
+                            index =
+                                matching(&chars, index, '(', ')').map_or(chars.len(), |end| end + 1)
+                        }
+                        Some('[') => {
+                            index =
+                                matching(&chars, index, '[', ']').map_or(chars.len(), |end| end + 1)
+                        }
+                        _ => {}
+                    }
+                }
+                None => {
+                    result.push(c);
+                    index += 1;
+                }
+            },
+            '<' => match chars[index..].iter().position(|&c| c == '>') {
+                Some(len) if len > 1 && !chars[index + 1].is_whitespace() => index += len + 1,
+                _ => {
+                    result.push(c);
+                    index += 1;
+                }
+            },
+            '*' => index += 1,
+            '_' | '~' => {
+                let run = chars[index..].iter().take_while(|&&m| m == c).count();
+                let before = index.checked_sub(1).map(|i| chars[i]);
+                let after = chars.get(index + run).copied();
+                let inside_word = before.is_some_and(char::is_alphanumeric)
+                    && after.is_some_and(char::is_alphanumeric);
+                if inside_word || (c == '~' && run == 1) {
+                    result.extend(&chars[index..index + run]);
+                }
+                index += run;
+            }
+            _ => {
+                result.push(c);
+                index += 1;
+            }
+        }
+    }
+
+    result
+}
+
+fn asciidoc_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        let rest: String = chars[index..].iter().take(8).collect();
+        let at_word_start = index == 0 || !chars[index - 1].is_alphanumeric();
+
+        if c == '`' {
+            match chars[index + 1..].iter().position(|&c| c == '`') {
+                Some(len) => index += len + 2,
+                None => {
+                    result.push(c);
+                    index += 1;
+                }
+            }
+        } else if c == '<' && rest.starts_with("<<") {
+            let end = (index..chars.len().saturating_sub(1))
+                .find(|&end| chars[end] == '>' && chars[end + 1] == '>')
+                .unwrap_or(chars.len());
+            let reference: String = chars[(index + 2).min(end)..end].iter().collect();
+            if let Some((_, label)) = reference.split_once(',') {
+                result.push_str(label.trim());
+            }
+            index = end + 2;
+        } else if at_word_start
+            && [
+                "link:",
+                "image:",
+                "footnote:",
+                "http://",
+                "https://",
+                "ftp://",
+                "mailto:",
+            ]
+            .iter()
+            .any(|prefix| rest.starts_with(prefix))
+        {
+            let target_end = chars[index..]
+                .iter()
+                .position(|&c| c == '[' || c.is_whitespace())
+                .map_or(chars.len(), |len| index + len);
+            // A target without a label, or with an unclosed one, stays as text.
+            let end = (chars.get(target_end) == Some(&'['))
+                .then(|| matching(&chars, target_end, '[', ']'))
+                .flatten();
+            if let Some(end) = end {
+                let label: String = chars[target_end + 1..end].iter().collect();
+                let label = label.split(',').next().unwrap_or("").trim_matches('"');
+                result.push_str(&asciidoc_inline(label));
+                index = end + 1;
+            } else {
+                result.extend(&chars[index..target_end]);
+                index = target_end;
+            }
+        } else if c == '*' || c == '_' || c == '#' {
+            let run = chars[index..].iter().take_while(|&&m| m == c).count();
+            let before = index.checked_sub(1).map(|i| chars[i]);
+            let after = chars.get(index + run).copied();
+            if before.is_some_and(char::is_alphanumeric) && after.is_some_and(char::is_alphanumeric)
+            {
+                result.extend(&chars[index..index + run]);
+            }
+            index += run;
+        } else {
+            result.push(c);
+            index += 1;
+        }
+    }
+
+    result
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(markup: Markup, text: &str) -> (Vec<String>, usize) {
+        let mut prose = Prose::new(markup);
+        let lines = text.lines().filter_map(|line| prose.line(line)).collect();
+        (lines, prose.code_lines)
+    }
+
+    #[test]
+    fn test_markup_for_path() {
+        let auto = ProseFormat::Auto;
+        assert_eq!(
+            Markup::for_path(Path::new("README.md"), &auto),
+            Markup::Markdown
+        );
+        assert_eq!(
+            Markup::for_path(Path::new("doc.adoc.gz"), &auto),
+            Markup::AsciiDoc
+        );
+        assert_eq!(
+            Markup::for_path(Path::new("index.HTML"), &auto),
+            Markup::Html
+        );
+        assert_eq!(
+            Markup::for_path(Path::new("notes.txt"), &auto),
+            Markup::Plain
+        );
+        assert_eq!(
+            Markup::for_path(Path::new("notes.txt"), &ProseFormat::Markdown),
+            Markup::Markdown
+        );
+    }
+
+    #[test]
+    fn test_markdown() {
+        let text = "---\ntitle: Doc\n---\n# The *Title* #\n\nSee [the docs](https://example.com/a b) and `x = 1`.\n\
+                    \n    indented code\n\n```rust\nfn main() {}\n\n```\n- [x] done <br/> item\n\n---\n\
+                    | a | b |\n|---|:-:|\n[docs]: https://example.com\nAT&amp;T ~~old~~ snake_case";
+        let (lines, code_lines) = filter(Markup::Markdown, text);
+
+        assert_eq!(
+            lines,
+            vec![
+                "The Title",
+                "",
+                "See the docs and .",
+                "",
+                "",
+                "done  item",
+                "",
+                "  a   b  ",
+                "AT&T old snake_case"
+            ]
+        );
+        assert_eq!(code_lines, 3);
+    }
+
+    #[test]
+    fn test_markdown_indented_continuation_is_prose() {
+        let (lines, code_lines) = filter(
+            Markup::Markdown,
+            "Some paragraph\n    still prose\n* item\n     more",
+        );
+
+        assert_eq!(lines, vec!["Some paragraph", "still prose", "item", "more"]);
+        assert_eq!(code_lines, 0);
+    }
+
+    #[test]
+    fn test_html() {
+        let text = "<html><head><style>\nbody { color: red; }\n</style></head>\n<body>\n<!-- a\ncomment -->\
+                    \n<p>Hello <a href=\"https://example.com\">world</a>&nbsp;&amp; <b>you</b></p>\n\
+                    <pre>\nlet x = 1;\n</pre><p>Use <code>wc</code>.</p>\n</body></html>";
+        let (lines, code_lines) = filter(Markup::Html, text);
+
+        assert_eq!(lines, vec![" Hello world\u{a0}& you ", " Use . "]);
+        assert_eq!(code_lines, 2);
+    }
+
+    #[test]
+    fn test_asciidoc() {
+        let text = "= Document Title\n:toc: left\n// a comment\n\n== Intro\n\nRead link:https://example.com[the guide] \
+                    or https://example.org[this] with `wc -l`.\n\n[source,rust]\n----\nfn main() {}\n----\n\n\
+                    * *bold* item\n.Block title\nimage::diagram.png[]\n<<intro,See intro>>\nsee link:foo[\n\n\
+                    [source]\nlet x = 1;";
+        let (lines, code_lines) = filter(Markup::AsciiDoc, text);
+
+        assert_eq!(
+            lines,
+            vec![
+                "Document Title",
+                "",
+                "Intro",
+                "",
+                "Read the guide or this with .",
+                "",
+                "",
+                "bold item",
+                "Block title",
+                "See intro",
+                "see link:foo[",
+                ""
+            ]
+        );
+        assert_eq!(code_lines, 2);
+    }
+}
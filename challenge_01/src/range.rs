@@ -0,0 +1,318 @@
+use crate::cli::Cli;
+use crate::decompress::{detect, Compression, HEADER_SIZE};
+use crate::progress::Progress;
+use crate::wc::{open_input, track};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+// Byte ranges are 0-based and exclude END, like a slice. Line ranges are
+// 1-based and include END, like `sed -n START,ENDp`, and always count '\n'
+// lines, so they cannot be combined with --record-sep. Either side may be
+// left out to mean the start or the end of the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Range {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+// The byte offsets that were actually counted, after decompression.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Span {
+    pub start: u64,
+    pub end: u64,
+}
+
+enum Limit {
+    Bytes(u64),
+    Lines(u64),
+}
+
+pub struct Window<R: BufRead> {
+    inner: R,
+    limit: Limit,
+    start: u64,
+    consumed: u64,
+}
+
+impl FromStr for Range {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (start, end) = value
+            .split_once(':')
+            .ok_or_else(|| "expected START:END".to_string())?;
+        let parse = |bound: &str| {
+            bound
+                .parse::<u64>()
+                .map_err(|_| format!("invalid bound '{}'", bound))
+        };
+
+        let start = if start.is_empty() { 0 } else { parse(start)? };
+        let end = if end.is_empty() {
+            None
+        } else {
+            Some(parse(end)?)
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err("END must not be before START".to_string());
+        }
+
+        Ok(Range { start, end })
+    }
+}
+
+impl Span {
+    pub fn format(&self) -> String {
+        format!(" (bytes {}..{})", self.start, self.end)
+    }
+}
+
+impl<R: BufRead> Window<R> {
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.start + self.consumed,
+        }
+    }
+}
+
+impl<R: BufRead> Read for Window<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Window<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let buf = self.inner.fill_buf()?;
+        let len = match self.limit {
+            Limit::Bytes(remaining) => buf.len().min(remaining.try_into().unwrap_or(usize::MAX)),
+            Limit::Lines(0) => 0,
+            Limit::Lines(remaining) => buf
+                .iter()
+                .enumerate()
+                .filter(|(_, &byte)| byte == b'\n')
+                .nth((remaining - 1).try_into().unwrap_or(usize::MAX))
+                .map_or(buf.len(), |(index, _)| index + 1),
+        };
+
+        Ok(&buf[..len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match &mut self.limit {
+            Limit::Bytes(remaining) => *remaining -= amt as u64,
+            Limit::Lines(remaining) => {
+                // The buffer is already filled, so this does not read again.
+                let newlines = match self.inner.fill_buf() {
+                    Ok(buf) => buf[..amt].iter().filter(|&&byte| byte == b'\n').count(),
+                    Err(_) => 0,
+                };
+                *remaining -= newlines as u64;
+            }
+        }
+        self.consumed += amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+// Opens the slice selected by --bytes-range or --lines-range, or returns None
+// when no range was asked for.
+pub fn open_range(
+    path: &Path,
+    args: &Cli,
+    progress: Option<&Arc<Progress>>,
+) -> Result<Option<Window<Box<dyn BufRead>>>, String> {
+    if let Some(range) = &args.bytes_range {
+        let limit = Limit::Bytes(range.end.map_or(u64::MAX, |end| end - range.start));
+        if let Some(reader) = seek_to(path, args, progress, range.start)? {
+            let len = path
+                .metadata()
+                .map_or(range.start, |metadata| metadata.len());
+            return Ok(Some(window(reader, limit, range.start.min(len))));
+        }
+
+        let mut reader = open_input(path, args, progress)?;
+        let start = io::copy(&mut (&mut reader).take(range.start), &mut io::sink())
+            .map_err(|_| "Error at reading the file".to_string())?;
+
+        return Ok(Some(window(reader, limit, start)));
+    }
+
+    if let Some(range) = &args.lines_range {
+        let mut reader = open_input(path, args, progress)?;
+        let mut start = 0;
+        let mut line = Vec::new();
+        for _ in 1..range.start {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(read) => start += read as u64,
+                Err(_) => return Err("Error at reading the file".to_string()),
+            }
+        }
+        let first = range.start.max(1);
+        let limit = Limit::Lines(
+            range
+                .end
+                .map_or(u64::MAX, |end| (end + 1).saturating_sub(first)),
+        );
+
+        return Ok(Some(window(reader, limit, start)));
+    }
+
+    Ok(None)
+}
+
+fn window(inner: Box<dyn BufRead>, limit: Limit, start: u64) -> Window<Box<dyn BufRead>> {
+    Window {
+        inner,
+        limit,
+        start,
+        consumed: 0,
+    }
+}
+
+// Only regular files of the working tree that report their size and are read
+// as is can be seeked into; compressed input has to be decompressed from the
+// beginning. Seeking past the end leaves an empty reader.
+fn seek_to(
+    path: &Path,
+    args: &Cli,
+    progress: Option<&Arc<Progress>>,
+    start: u64,
+) -> Result<Option<Box<dyn BufRead>>, String> {
+    if args.rev.is_some() {
+        return Ok(None);
+    }
+    let mut file = match File::open(path) {
+        Ok(file)
+            if file
+                .metadata()
+                .is_ok_and(|metadata| metadata.is_file() && metadata.len() > 0) =>
+        {
+            file
+        }
+        _ => return Ok(None),
+    };
+    if !args.raw {
+        let mut header = Vec::new();
+        (&mut file)
+            .take(HEADER_SIZE)
+            .read_to_end(&mut header)
+            .map_err(|_| "Error at reading the file".to_string())?;
+        let raw_deflate = path.extension().is_some_and(|ext| ext == "deflate");
+        if raw_deflate || detect(&header) != Compression::None {
+            return Ok(None);
+        }
+    }
+
+    file.seek(SeekFrom::Start(start))
+        .map_err(|_| "Error at reading the file".to_string())?;
+
+    Ok(Some(match args.max_bytes {
+        Some(max_bytes) => track(BufReader::new(file.take(max_bytes)), progress),
+        None => track(BufReader::new(file), progress),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(data: &str, limit: Limit) -> (String, u64) {
+        let mut window = Window {
+            inner: BufReader::with_capacity(4, data.as_bytes()),
+            limit,
+            start: 0,
+            consumed: 0,
+        };
+        let mut text = String::new();
+        window
+            .read_to_string(&mut text)
+            .expect("failed to read window");
+
+        (text, window.span().end)
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            "3:7".parse(),
+            Ok(Range {
+                start: 3,
+                end: Some(7)
+            })
+        );
+        assert_eq!(
+            ":7".parse(),
+            Ok(Range {
+                start: 0,
+                end: Some(7)
+            })
+        );
+        assert_eq!(
+            "3:".parse(),
+            Ok(Range {
+                start: 3,
+                end: None
+            })
+        );
+        assert!("7:3".parse::<Range>().is_err());
+        assert!("3-7".parse::<Range>().is_err());
+        assert!(<Cli as clap::Parser>::try_parse_from([
+            "wc",
+            "--lines-range=2:3",
+            "--record-sep=\\0",
+            "a.txt"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_window_limits() {
+        let data = "one\ntwo\nthree\nfour\n";
+
+        assert_eq!(read(data, Limit::Bytes(6)), ("one\ntw".to_string(), 6));
+        assert_eq!(
+            read(data, Limit::Lines(3)),
+            ("one\ntwo\nthree\n".to_string(), 14)
+        );
+        assert_eq!(read(data, Limit::Lines(9)), (data.to_string(), 19));
+        assert_eq!(read(data, Limit::Lines(0)), (String::new(), 0));
+    }
+
+    #[test]
+    fn test_open_range() {
+        let path = std::env::temp_dir().join(format!("wc_range_{}.txt", std::process::id()));
+        std::fs::write(&path, "one\ntwo\nthree\nfour\n").expect("failed to create test file");
+        let path_arg = path.to_str().unwrap();
+        let slice = |option: &str| {
+            let args = <Cli as clap::Parser>::parse_from(["wc", option, path_arg]);
+            let mut window = open_range(&path, &args, None).unwrap().unwrap();
+            let mut text = String::new();
+            window.read_to_string(&mut text).unwrap();
+            (text, window.span())
+        };
+
+        let bytes = slice("--bytes-range=4:11");
+        let lines = slice("--lines-range=2:3");
+        let past_end = slice("--bytes-range=100:");
+        std::fs::remove_file(&path).expect("error when removing test file");
+
+        assert_eq!(bytes, ("two\nthr".to_string(), Span { start: 4, end: 11 }));
+        assert_eq!(
+            lines,
+            ("two\nthree\n".to_string(), Span { start: 4, end: 14 })
+        );
+        assert_eq!(past_end, (String::new(), Span { start: 19, end: 19 }));
+    }
+}
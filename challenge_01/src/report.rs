@@ -0,0 +1,198 @@
+use crate::check::Violation;
+use crate::cli::{Cli, OutputFormat};
+use crate::csv::{get_csv_statistics, CsvStats};
+use crate::dups::{get_dup_statistics, DupStats};
+use crate::estimate::get_estimate;
+use crate::group::Group;
+use crate::ngrams::{get_ngram_statistics, NgramStats};
+use crate::numbers::NumberStyle;
+use crate::per_line::LineStats;
+use crate::progress::Progress;
+use crate::template::Template;
+use crate::wc::{get_statistics, ResultWc};
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum FileReport {
+    Csv(CsvStats),
+    Dups(DupStats),
+    Ngrams(NgramStats),
+}
+
+impl FileReport {
+    pub fn format(&self) -> String {
+        match self {
+            FileReport::Csv(stats) => stats.format(),
+            FileReport::Dups(stats) => stats.format(),
+            FileReport::Ngrams(stats) => stats.format(),
+        }
+    }
+}
+
+// The csv, dups and n-gram modes report on each file on its own, with no total.
+pub fn is_per_file(args: &Cli) -> bool {
+    args.csv || args.tsv || args.dups.is_some() || args.ngrams.is_some()
+}
+
+pub fn get_file_report(
+    path: &Path,
+    args: &Cli,
+    progress: Option<&Arc<Progress>>,
+) -> Result<FileReport, String> {
+    if let Some(mode) = &args.dups {
+        get_dup_statistics(path, args, mode, progress).map(FileReport::Dups)
+    } else if let Some(n) = args.ngrams {
+        get_ngram_statistics(path, args, n as usize, progress).map(FileReport::Ngrams)
+    } else {
+        get_csv_statistics(path, args, progress).map(FileReport::Csv)
+    }
+}
+
+pub fn get_result(
+    path: &Path,
+    args: &Cli,
+    progress: Option<&Arc<Progress>>,
+) -> Result<ResultWc, String> {
+    let result = if args.estimate {
+        get_estimate(path, args)
+    } else {
+        get_statistics(path, args, progress)
+    };
+
+    result.map(|result| ResultWc {
+        path: path.to_path_buf(),
+        ..result
+    })
+}
+
+pub fn print_results(results: &[ResultWc], total: Option<&ResultWc>, args: &Cli) {
+    match args.format {
+        OutputFormat::Plain => {
+            let style = NumberStyle::from_args(args);
+            for result in results {
+                print!("{}", line(row(result, args.printf.as_ref(), &style)));
+            }
+            if let Some(total) = total {
+                print!("{}", line(row(total, total_template(args), &style)));
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", json!({ "files": results, "total": total }));
+        }
+    }
+}
+
+pub fn print_groups(groups: &[Group], total: &ResultWc, args: &Cli) {
+    match args.format {
+        OutputFormat::Plain => {
+            let style = NumberStyle::from_args(args);
+            for group in groups {
+                match &args.printf {
+                    Some(template) => print!("{}", line(template.render(&group.result, &style))),
+                    None => println!("{} ({} files)", group.result.format(&style), group.files),
+                }
+            }
+            print!("{}", line(row(total, total_template(args), &style)));
+        }
+        OutputFormat::Json => {
+            println!("{}", json!({ "groups": groups, "total": total }));
+        }
+    }
+}
+
+pub fn print_line_stats(stats: &LineStats, args: &Cli) {
+    match args.format {
+        OutputFormat::Plain => println!("{}", stats.format()),
+        OutputFormat::Json => println!("{}", json!(stats)),
+    }
+}
+
+pub fn print_error(name: &str, err: &str, args: &Cli) {
+    match &args.printf_error {
+        Some(template) => print!("{}", line(template.render_error(name, err))),
+        None => eprintln!("{}: {}", name, err),
+    }
+}
+
+fn row(result: &ResultWc, template: Option<&Template>, style: &NumberStyle) -> String {
+    match template {
+        Some(template) => template.render(result, style),
+        None => result.format(style),
+    }
+}
+
+// Rows end with a newline, unless the template already ends with one.
+fn line(mut row: String) -> String {
+    if !row.ends_with('\n') {
+        row.push('\n');
+    }
+    row
+}
+
+// The total row falls back to the per-file template.
+fn total_template(args: &Cli) -> Option<&Template> {
+    args.printf_total.as_ref().or(args.printf.as_ref())
+}
+
+pub fn print_violations(violations: &[Violation], errors: usize, args: &Cli) {
+    match args.format {
+        OutputFormat::Plain => {
+            for violation in violations {
+                println!("{}", violation.format());
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", json!({ "violations": violations, "errors": errors }));
+        }
+    }
+}
+
+// Reports of the per-file modes are printed as soon as each file is done.
+pub fn render_report<T: Serialize>(report: &T, plain: String, args: &Cli) -> String {
+    match args.format {
+        OutputFormat::Plain => plain,
+        OutputFormat::Json => format!("{}\n", json!(report)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_render_report() {
+        let mut result = ResultWc::total();
+        result.file_name = "a.txt".to_string();
+        result.lines = 2;
+        let plain = Cli::parse_from(["wc", "a.txt"]);
+        let json = Cli::parse_from(["wc", "--format=json", "a.txt"]);
+
+        assert_eq!(
+            render_report(&result, "plain\n".to_string(), &plain),
+            "plain\n"
+        );
+        assert_eq!(
+            render_report(&result, "plain\n".to_string(), &json),
+            "{\"bytes\":0,\"chars\":0,\"lines\":2,\"name\":\"a.txt\",\"words\":0}\n"
+        );
+    }
+
+    #[test]
+    fn test_template_rows_end_once() {
+        let args = Cli::parse_from(["wc", "--printf=%n %l\\n", "a.txt"]);
+        let mut result = ResultWc::total();
+        result.file_name = "a.txt".to_string();
+        result.lines = 2;
+
+        assert_eq!(
+            line(row(&result, args.printf.as_ref(), &NumberStyle::Plain)),
+            "a.txt 2\n"
+        );
+        assert_eq!(line(row(&result, None, &NumberStyle::Plain)), "2 a.txt\n");
+    }
+}
@@ -0,0 +1,106 @@
+use crate::cli::{Cli, SortKey};
+use crate::wc::ResultWc;
+
+// A filter or sort on a count that was not asked for needs it counted all
+// the same, otherwise every file would compare as 0.
+pub fn enable_selected_counts(args: &mut Cli) {
+    let lines = args.min_lines > 0 || matches!(args.sort, Some(SortKey::Lines));
+    let words = args.min_words > 0 || matches!(args.sort, Some(SortKey::Words));
+    let chars = args.min_chars > 0 || matches!(args.sort, Some(SortKey::Chars));
+    let bytes = args.min_bytes > 0 || matches!(args.sort, Some(SortKey::Bytes));
+
+    args.count_lines |= lines;
+    args.count_words |= words;
+    args.count_chars |= chars;
+    args.count_bytes |= bytes;
+}
+
+pub fn select(mut results: Vec<ResultWc>, args: &Cli) -> Vec<ResultWc> {
+    results.retain(|result| {
+        result.lines >= args.min_lines
+            && result.words >= args.min_words
+            && result.chars >= args.min_chars
+            && result.bytes >= args.min_bytes
+    });
+
+    // Counts sort biggest first, names alphabetically.
+    if let Some(key) = &args.sort {
+        results.sort_by(|a, b| match key {
+            SortKey::Lines => b.lines.cmp(&a.lines),
+            SortKey::Words => b.words.cmp(&a.words),
+            SortKey::Chars => b.chars.cmp(&a.chars),
+            SortKey::Bytes => b.bytes.cmp(&a.bytes),
+            SortKey::Name => a.file_name.cmp(&b.file_name),
+        });
+    }
+    if args.reverse {
+        results.reverse();
+    }
+    if let Some(top) = args.top {
+        results.truncate(top);
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn result(file_name: &str, lines: usize, words: usize) -> ResultWc {
+        let mut result = ResultWc::total();
+        result.file_name = file_name.to_string();
+        result.lines = lines;
+        result.words = words;
+        result
+    }
+
+    fn names(args: &[&str]) -> Vec<String> {
+        let args = Cli::parse_from(args);
+        let results = vec![
+            result("b", 10, 50),
+            result("a", 700, 20),
+            result("c", 500, 90),
+            result("d", 700, 10),
+        ];
+
+        select(results, &args)
+            .into_iter()
+            .map(|result| result.file_name)
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_and_reverse() {
+        assert_eq!(names(&["wc", "--sort=lines", "x"]), ["a", "d", "c", "b"]);
+        assert_eq!(names(&["wc", "--sort=words", "x"]), ["c", "b", "a", "d"]);
+        assert_eq!(
+            names(&["wc", "--sort=name", "--reverse", "x"]),
+            ["d", "c", "b", "a"]
+        );
+        assert_eq!(names(&["wc", "--reverse", "x"]), ["d", "c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_enable_selected_counts() {
+        let mut args = Cli::parse_from(["wc", "-w", "--min-lines", "500", "--sort=bytes", "x"]);
+        enable_selected_counts(&mut args);
+
+        assert!(args.count_words && args.count_lines && args.count_bytes);
+        assert!(!args.count_chars);
+    }
+
+    #[test]
+    fn test_filter_and_top() {
+        assert_eq!(names(&["wc", "--min-lines", "500", "x"]), ["a", "c", "d"]);
+        assert_eq!(
+            names(&["wc", "--min-lines", "500", "--min-words", "15", "x"]),
+            ["a", "c"]
+        );
+        assert_eq!(
+            names(&["wc", "--sort=lines", "--top", "2", "x"]),
+            ["a", "d"]
+        );
+    }
+}
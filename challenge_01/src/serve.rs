@@ -0,0 +1,245 @@
+use crate::cli::Cli;
+use crate::config::filter_paths;
+use crate::git::collect_rev_paths;
+use crate::group::group;
+use crate::per_line::for_each_line;
+use crate::pool;
+use crate::report::{get_file_report, get_result, is_per_file};
+use crate::select::{enable_selected_counts, select};
+use crate::template::enable_counts;
+use crate::walk::collect_paths;
+use crate::wc::ResultWc;
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+// A request holds the same options and paths as a command line. Relative
+// paths are resolved against `cwd` when it is given.
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    options: Vec<String>,
+    #[serde(default)]
+    paths: Vec<String>,
+    cwd: Option<PathBuf>,
+}
+
+pub fn serve(socket: &Path) -> Result<(), String> {
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err("Socket is already in use".to_string());
+        }
+        fs::remove_file(socket).map_err(|_| "Error at removing the stale socket".to_string())?;
+    }
+
+    let listener =
+        UnixListener::bind(socket).map_err(|_| "Error at binding the socket".to_string())?;
+    listen(listener);
+
+    Ok(())
+}
+
+fn listen(listener: UnixListener) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || handle(stream));
+            }
+            Err(_) => eprintln!("Error at accepting a connection"),
+        }
+    }
+}
+
+// A connection may send any number of requests, one JSON object per line,
+// and gets one JSON line back for each of them.
+fn handle(stream: UnixStream) {
+    let reader = match stream.try_clone() {
+        Ok(reader) => BufReader::new(reader),
+        Err(_) => return,
+    };
+    let mut writer = stream;
+
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if writeln!(writer, "{}", respond(&line)).is_err() {
+            return;
+        }
+    }
+}
+
+pub fn respond(request: &str) -> Value {
+    match run(request) {
+        Ok(response) => response,
+        Err(err) => json!({ "error": err }),
+    }
+}
+
+fn run(request: &str) -> Result<Value, String> {
+    let request: Request =
+        serde_json::from_str(request).map_err(|err| format!("Invalid request: {}", err))?;
+
+    let mut argv = vec!["wc".to_string()];
+    argv.extend(request.options);
+    if !request.paths.is_empty() {
+        argv.push("--".to_string());
+        argv.extend(request.paths);
+    }
+    let mut args = Cli::try_parse_from(argv).map_err(|err| {
+        let message = err.to_string();
+        message.lines().next().unwrap_or_default().to_string()
+    })?;
+    enable_counts(&mut args);
+    enable_selected_counts(&mut args);
+    if args.serve.is_some() || args.command.is_some() || args.show_config {
+        return Err("Requests cannot start a server or a client".to_string());
+    }
+    if let Some(cwd) = &request.cwd {
+        args.paths = args.paths.iter().map(|path| cwd.join(path)).collect();
+    }
+
+    let paths = match &args.rev {
+        Some(rev) => collect_rev_paths(&args.paths, rev, args.recursive),
+        None => collect_paths(&args.paths, args.recursive),
+    };
+    let paths = filter_paths(paths, &args)?;
+    let mut errors = Vec::new();
+    let mut error = |path: &Path, err: String| {
+        errors.push(json!({ "name": path.display().to_string(), "error": err }));
+    };
+
+    if args.per_line.is_some() {
+        let mut lines = Vec::new();
+        for path in &paths {
+            if let Err(err) = for_each_line(path, &args, |stats| lines.push(stats)) {
+                error(path, err);
+            }
+        }
+        return Ok(json!({ "lines": lines, "errors": errors }));
+    }
+
+    if is_per_file(&args) {
+        let mut reports = Vec::new();
+        let outcomes = pool::run(&paths, args.jobs as usize, |path| {
+            get_file_report(path, &args, None)
+        });
+        for (path, outcome) in paths.iter().zip(outcomes) {
+            match outcome {
+                Ok(report) => reports.push(report),
+                Err(err) => error(path, err),
+            }
+        }
+        return Ok(json!({ "files": reports, "errors": errors }));
+    }
+
+    let mut results = Vec::new();
+    let outcomes = pool::run(&paths, args.jobs as usize, |path| {
+        get_result(path, &args, None)
+    });
+    for (path, outcome) in paths.iter().zip(outcomes) {
+        match outcome {
+            Ok(result) => results.push(result),
+            Err(err) => error(path, err),
+        }
+    }
+    let mut total = ResultWc::total();
+    for result in &results {
+        total.add(result);
+    }
+
+    Ok(match &args.group_by {
+        Some(by) => json!({ "groups": group(&results, by), "total": total, "errors": errors }),
+        None => {
+            let total = (paths.len() > 1).then_some(&total);
+            json!({ "files": select(results, &args), "total": total, "errors": errors })
+        }
+    })
+}
+
+// Sends a single request built from a command line and returns the raw
+// response line.
+pub fn client(socket: &Path, options: &[String]) -> Result<String, String> {
+    let mut stream =
+        UnixStream::connect(socket).map_err(|_| "Error at connecting to the socket".to_string())?;
+    let request = json!({ "options": options, "cwd": std::env::current_dir().ok() });
+    writeln!(stream, "{}", request).map_err(|_| "Error at sending the request".to_string())?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .map_err(|_| "Error at reading the response".to_string())?;
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wc_serve_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_respond_counts() {
+        let file = temp_path("counts.txt");
+        fs::write(&file, "one two\nthree\n").expect("failed to create test file");
+        let request = json!({
+            "options": ["-l", "-w"],
+            "paths": [file.file_name().unwrap().to_str(), "missing.txt"],
+            "cwd": std::env::temp_dir(),
+        });
+
+        let response = respond(&request.to_string());
+        fs::remove_file(&file).expect("error when removing test file");
+
+        assert_eq!(response["files"][0]["lines"], 2);
+        assert_eq!(response["files"][0]["words"], 3);
+        assert_eq!(response["total"]["words"], 3);
+        assert_eq!(response["errors"][0]["error"], "Error at opening the file");
+    }
+
+    #[test]
+    fn test_respond_errors() {
+        assert!(respond("not json")["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Invalid request"));
+        assert!(respond(r#"{"options": ["--nope", "a.txt"]}"#)["error"]
+            .as_str()
+            .unwrap()
+            .contains("--nope"));
+        assert_eq!(
+            respond(r#"{"options": ["--serve", "other.sock"]}"#)["error"],
+            "Requests cannot start a server or a client"
+        );
+    }
+
+    #[test]
+    fn test_serve_and_client() {
+        let socket = temp_path("test.sock");
+        let file = temp_path("client.txt");
+        fs::write(&file, "a b c\n").expect("failed to create test file");
+        let listener = UnixListener::bind(&socket).expect("failed to bind test socket");
+        thread::spawn(move || listen(listener));
+
+        let options = ["-w".to_string(), file.display().to_string()];
+        let first = client(&socket, &options).expect("failed to send request");
+        let second = client(&socket, &options).expect("failed to send request");
+        fs::remove_file(&socket).expect("error when removing test socket");
+        fs::remove_file(&file).expect("error when removing test file");
+
+        let response: Value = serde_json::from_str(&first).expect("invalid response");
+        assert_eq!(response["files"][0]["words"], 3);
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,162 @@
+use std::fs::{self, File, Metadata};
+use std::io::Read;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+
+// Character devices that never reach end of file.
+const ENDLESS_DEVICES: [&str; 4] = ["/dev/zero", "/dev/full", "/dev/random", "/dev/urandom"];
+
+// Everything that is not a directory or a socket is read as a stream, so the
+// reported size is never trusted: FIFOs and /proc files report 0 and are read
+// until end of file like any regular file.
+pub fn check_input(path: &Path, max_bytes: Option<u64>) -> Result<(), String> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Err("Error at opening the file".to_string()),
+    };
+    let file_type = metadata.file_type();
+
+    if file_type.is_dir() {
+        return Err("Is a directory".to_string());
+    }
+    if file_type.is_socket() {
+        return Err("Is a socket".to_string());
+    }
+    if max_bytes.is_none() && is_endless(&metadata) {
+        return Err("Is an endless device, use --max-bytes to read part of it".to_string());
+    }
+
+    Ok(())
+}
+
+fn is_endless(metadata: &Metadata) -> bool {
+    metadata.file_type().is_char_device()
+        && ENDLESS_DEVICES.iter().any(|device| {
+            fs::metadata(device).is_ok_and(|device| {
+                device.file_type().is_char_device() && device.rdev() == metadata.rdev()
+            })
+        })
+}
+
+// Pseudo-files such as those in /proc are regular files that report a size of
+// 0 but still have content.
+pub fn has_reliable_size(file: &File, metadata: &Metadata) -> bool {
+    if !metadata.is_file() {
+        return false;
+    }
+    if metadata.len() > 0 {
+        return true;
+    }
+
+    let mut byte = [0; 1];
+    !matches!((&*file).read(&mut byte), Ok(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use crate::numbers::NumberStyle;
+    use crate::wc::{file_name, get_statistics};
+    use clap::Parser;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use std::thread;
+
+    fn fifo(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("wc_special_{}_{}", std::process::id(), name));
+        let status = Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .expect("failed to run mkfifo");
+        assert!(status.success(), "failed to create test fifo");
+        path
+    }
+
+    fn count(path: &Path, options: &[&str]) -> Result<String, String> {
+        let path = path.to_str().unwrap();
+        let args = Cli::parse_from(["wc"].iter().chain(options).chain(&[path]));
+
+        get_statistics(&args.paths[0], &args, None).map(|result| result.format(&NumberStyle::Plain))
+    }
+
+    #[test]
+    fn test_fifo() {
+        let path = fifo("plain");
+        let writer_path = path.clone();
+        let writer = thread::spawn(move || {
+            let mut fifo = File::options()
+                .write(true)
+                .open(writer_path)
+                .expect("failed to open fifo");
+            fifo.write_all(b"one two\nthree\n")
+                .expect("failed to write to fifo");
+        });
+
+        let result = count(&path, &["-l", "-w"]);
+        writer.join().expect("fifo writer panicked");
+        fs::remove_file(&path).expect("error when removing test fifo");
+
+        assert_eq!(result, Ok(format!("2 3 {}", file_name(&path))));
+    }
+
+    #[test]
+    fn test_fifo_with_gzip() {
+        let path = fifo("gzip");
+        let writer_path = path.clone();
+        let writer = thread::spawn(move || {
+            let fifo = File::options()
+                .write(true)
+                .open(writer_path)
+                .expect("failed to open fifo");
+            let mut encoder = flate2::write::GzEncoder::new(fifo, flate2::Compression::default());
+            encoder
+                .write_all(b"a b c\n")
+                .expect("failed to write to fifo");
+            encoder.finish().expect("failed to finish gzip stream");
+        });
+
+        let result = count(&path, &["-w"]);
+        writer.join().expect("fifo writer panicked");
+        fs::remove_file(&path).expect("error when removing test fifo");
+
+        assert_eq!(result, Ok(format!("3 {}", file_name(&path))));
+    }
+
+    #[test]
+    fn test_socket_and_endless_devices() {
+        let socket = std::env::temp_dir().join(format!("wc_special_{}.sock", std::process::id()));
+        let listener = std::os::unix::net::UnixListener::bind(&socket).expect("failed to bind");
+        let result = count(&socket, &["-l"]);
+        drop(listener);
+        fs::remove_file(&socket).expect("error when removing test socket");
+
+        assert_eq!(result, Err("Is a socket".to_string()));
+        assert!(count(Path::new("/dev/zero"), &["-l"]).is_err());
+        assert_eq!(
+            count(Path::new("/dev/zero"), &["-b", "--max-bytes=10"]),
+            Ok("10 zero".to_string())
+        );
+        assert_eq!(
+            count(Path::new("/dev/null"), &["-l"]),
+            Ok("null".to_string())
+        );
+    }
+
+    #[test]
+    fn test_proc_file_without_size() {
+        let path = Path::new("/proc/self/status");
+        if !path.exists() {
+            return;
+        }
+        let file = File::open(path).expect("failed to open proc file");
+        let metadata = file.metadata().expect("failed to read metadata");
+
+        assert_eq!(metadata.len(), 0);
+        assert!(!has_reliable_size(&file, &metadata));
+        assert!(count(path, &["-l"])
+            .unwrap()
+            .starts_with(|c: char| c.is_ascii_digit()));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::cli::Encoding;
+use regex::Regex;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+// A word delimiter is either a set of characters or, written as /REGEX/,
+// a regular expression.
+#[derive(Clone, Debug)]
+pub enum WordRule {
+    Chars(Vec<char>),
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Separator(pub Vec<u8>);
+
+pub struct Records<R: BufRead> {
+    reader: R,
+    separator: Vec<u8>,
+    crlf: bool,
+}
+
+impl FromStr for WordRule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(pattern) = value
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            return Regex::new(pattern)
+                .map(WordRule::Regex)
+                .map_err(|err| err.to_string());
+        }
+
+        let chars: Vec<char> = String::from_utf8(unescape(value)?)
+            .map_err(|_| "delimiters must be valid UTF-8".to_string())?
+            .chars()
+            .collect();
+        if chars.is_empty() {
+            return Err("expected at least one delimiter".to_string());
+        }
+
+        Ok(WordRule::Chars(chars))
+    }
+}
+
+impl WordRule {
+    pub fn split<'a>(&self, record: &'a str) -> Vec<&'a str> {
+        match self {
+            WordRule::Chars(chars) => record
+                .split(|c| chars.contains(&c))
+                .filter(|word| !word.is_empty())
+                .collect(),
+            WordRule::Regex(regex) => regex
+                .split(record)
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn count(&self, record: &str) -> usize {
+        self.split(record).len()
+    }
+}
+
+pub fn words<'a>(rule: Option<&WordRule>, record: &'a str) -> Vec<&'a str> {
+    match rule {
+        Some(rule) => rule.split(record),
+        None => record.split_whitespace().collect(),
+    }
+}
+
+// Splits the input on --record-sep, or into lines when it is not given.
+// Records are left undecoded so that byte counts stay exact whatever the
+// encoding policy.
+pub fn records<'a, R: BufRead + 'a>(
+    reader: R,
+    separator: Option<&Separator>,
+) -> Box<dyn Iterator<Item = io::Result<Vec<u8>>> + 'a> {
+    Box::new(terminated_records(reader, separator).map(|record| record.map(|(record, _)| record)))
+}
+
+// Also gives the bytes that ended each record: the separator, "\n" or "\r\n"
+// for lines, and nothing for a last record the input does not terminate.
+pub fn terminated_records<R: BufRead>(reader: R, separator: Option<&Separator>) -> Records<R> {
+    match separator {
+        Some(separator) => Records::new(reader, separator),
+        None => Records {
+            reader,
+            separator: b"\n".to_vec(),
+            crlf: true,
+        },
+    }
+}
+
+pub fn decode(record: Vec<u8>, encoding: &Encoding) -> io::Result<String> {
+    match encoding {
+        Encoding::Utf8 => {
+            String::from_utf8(record).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        }
+        Encoding::Lossy => Ok(String::from_utf8_lossy(&record).into_owned()),
+        Encoding::Latin1 => Ok(record.into_iter().map(char::from).collect()),
+    }
+}
+
+impl FromStr for Separator {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let separator = unescape(value)?;
+        if separator.is_empty() {
+            return Err("the separator cannot be empty".to_string());
+        }
+
+        Ok(Separator(separator))
+    }
+}
+
+// Supports \0, \n, \r, \t, \\ and \xHH so NUL and other bytes can be typed.
+pub fn unescape(value: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('0') => bytes.push(0),
+            Some('n') => bytes.push(b'\n'),
+            Some('r') => bytes.push(b'\r'),
+            Some('t') => bytes.push(b'\t'),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .map_err(|_| format!("invalid escape \\x{}", hex))?;
+                bytes.push(byte);
+            }
+            Some(other) => return Err(format!("invalid escape \\{}", other)),
+            None => return Err("trailing backslash".to_string()),
+        }
+    }
+
+    Ok(bytes)
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(reader: R, separator: &Separator) -> Self {
+        Records {
+            reader,
+            separator: separator.0.clone(),
+            crlf: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = io::Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = *self.separator.last()?;
+        let mut record = Vec::new();
+        let mut end = Vec::new();
+
+        loop {
+            match self.reader.read_until(last, &mut record) {
+                Ok(0) if record.is_empty() => return None,
+                Ok(0) => break,
+                Ok(_) if record.ends_with(&self.separator) => {
+                    end = record.split_off(record.len() - self.separator.len());
+                    break;
+                }
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        if self.crlf && record.ends_with(b"\r") {
+            end.splice(0..0, record.pop());
+        }
+
+        Some(Ok((record, end)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(data: &str, separator: &str) -> Vec<String> {
+        let separator: Separator = separator.parse().expect("invalid separator");
+        Records::new(data.as_bytes(), &separator)
+            .map(|record| String::from_utf8(record.expect("failed to read record").0).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_records_nul_separated() {
+        assert_eq!(records("./a b\0./c\0", "\\0"), ["./a b", "./c"]);
+        assert_eq!(records("./a\0./c", "\\0"), ["./a", "./c"]);
+        assert!(records("", "\\0").is_empty());
+    }
+
+    #[test]
+    fn test_records_multi_byte_separator() {
+        assert_eq!(records("a=1;;b=2;c;;", ";;"), ["a=1", "b=2;c"]);
+        assert_eq!(records("x<EOR>y<E<EOR>", "<EOR>"), ["x", "y<E"]);
+    }
+
+    #[test]
+    fn test_record_terminators() {
+        let ends = |data: &str| -> Vec<(String, String)> {
+            terminated_records(data.as_bytes(), None)
+                .map(|record| {
+                    let (record, end) = record.expect("failed to read record");
+                    (
+                        String::from_utf8(record).unwrap(),
+                        String::from_utf8(end).unwrap(),
+                    )
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            ends("a\r\nb\nc"),
+            [
+                ("a".to_string(), "\r\n".to_string()),
+                ("b".to_string(), "\n".to_string()),
+                ("c".to_string(), String::new())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_separator_escapes() {
+        assert_eq!("\\x1e".parse(), Ok(Separator(vec![0x1e])));
+        assert_eq!("\\r\\n".parse(), Ok(Separator(b"\r\n".to_vec())));
+        assert!("".parse::<Separator>().is_err());
+        assert!("\\q".parse::<Separator>().is_err());
+    }
+
+    #[test]
+    fn test_word_rules() {
+        let chars: WordRule = ",;\\t".parse().expect("invalid delimiters");
+        let regex: WordRule = "/[,;]\\s*/".parse().expect("invalid regex");
+
+        assert_eq!(chars.count("a,b;;c\td e"), 4);
+        assert_eq!(regex.count("a, b;c d,"), 3);
+        assert!("/[/".parse::<WordRule>().is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::cli::Cli;
+use crate::numbers::NumberStyle;
+use crate::split::unescape;
+use crate::wc::ResultWc;
+use std::str::FromStr;
+
+const MAX_WIDTH: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Lines,
+    Words,
+    Chars,
+    Bytes,
+    MaxLineLength,
+    Name,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Text(String),
+    Field {
+        field: Field,
+        width: usize,
+        left: bool,
+    },
+}
+
+// Placeholders follow wc's options: %l lines, %w words, %m chars, %c bytes,
+// %L max line length, %n name and %e the error of an error row. A width such
+// as %8l pads on the left, %-20n on the right, up to MAX_WIDTH. \t, \n and
+// friends are expanded like in --record-sep. Each row gets a newline unless
+// the template ends with one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = String::from_utf8(unescape(value)?)
+            .map_err(|_| "templates must be valid UTF-8".to_string())?;
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = value.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                text.push(c);
+                continue;
+            }
+            if chars.next_if_eq(&'%').is_some() {
+                text.push('%');
+                continue;
+            }
+
+            let left = chars.next_if_eq(&'-').is_some();
+            let mut width: usize = 0;
+            while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                width = width
+                    .checked_mul(10)
+                    .and_then(|width| width.checked_add(digit as usize))
+                    .filter(|&width| width <= MAX_WIDTH)
+                    .ok_or_else(|| format!("widths cannot go over {}", MAX_WIDTH))?;
+                chars.next();
+            }
+            let field = match chars.next() {
+                Some('l') => Field::Lines,
+                Some('w') => Field::Words,
+                Some('m') => Field::Chars,
+                Some('c') => Field::Bytes,
+                Some('L') => Field::MaxLineLength,
+                Some('n') => Field::Name,
+                Some('e') => Field::Error,
+                Some(other) => return Err(format!("unknown placeholder %{}", other)),
+                None => return Err("unfinished placeholder at the end".to_string()),
+            };
+
+            if !text.is_empty() {
+                parts.push(Part::Text(std::mem::take(&mut text)));
+            }
+            parts.push(Part::Field { field, width, left });
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Template { parts })
+    }
+}
+
+impl Template {
+    pub fn render(&self, result: &ResultWc, style: &NumberStyle) -> String {
+        self.fill(|field| match field {
+            Field::Lines => style.count(result.lines),
+            Field::Words => style.count(result.words),
+            Field::Chars => style.count(result.chars),
+            Field::Bytes => style.bytes(result.bytes),
+            Field::MaxLineLength => style.count(result.max_line_length.unwrap_or(0)),
+            Field::Name => result.file_name.clone(),
+            Field::Error => String::new(),
+        })
+    }
+
+    // Counts are left empty in error rows.
+    pub fn render_error(&self, name: &str, err: &str) -> String {
+        self.fill(|field| match field {
+            Field::Name => name.to_string(),
+            Field::Error => err.to_string(),
+            _ => String::new(),
+        })
+    }
+
+    fn fill(&self, value: impl Fn(Field) -> String) -> String {
+        let mut result = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => result.push_str(text),
+                Part::Field {
+                    field,
+                    width,
+                    left: true,
+                } => result.push_str(&format!("{:<width$}", value(*field), width = width)),
+                Part::Field { field, width, .. } => {
+                    result.push_str(&format!("{:>width$}", value(*field), width = width))
+                }
+            }
+        }
+
+        result
+    }
+
+    fn uses(&self, wanted: Field) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Field { field, .. } if *field == wanted))
+    }
+}
+
+// Whatever the templates print gets counted, even without the matching flags.
+pub fn enable_counts(args: &mut Cli) {
+    let templates: Vec<Template> = [&args.printf, &args.printf_total]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    for template in templates {
+        args.count_lines |= template.uses(Field::Lines);
+        args.count_words |= template.uses(Field::Words);
+        args.count_chars |= template.uses(Field::Chars);
+        args.count_bytes |= template.uses(Field::Bytes);
+        args.max_line_length |= template.uses(Field::MaxLineLength);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_render() {
+        let template: Template = "%-6n|%4l|%w|%c%%\\t%L".parse().expect("invalid template");
+        let mut result = ResultWc::total();
+        result.file_name = "a.rs".to_string();
+        result.lines = 12;
+        result.words = 1500;
+        result.bytes = 2048;
+
+        assert_eq!(
+            template.render(&result, &NumberStyle::Plain),
+            "a.rs  |  12|1500|2048%\t0"
+        );
+        assert_eq!(
+            template.render(&result, &NumberStyle::Thousands(',')),
+            "a.rs  |  12|1,500|2,048%\t0"
+        );
+        assert_eq!(
+            template.render_error("b.rs", "Is a directory"),
+            "b.rs  |    ||%\t"
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert_eq!(
+            "%l %q".parse::<Template>(),
+            Err("unknown placeholder %q".to_string())
+        );
+        assert!("%-8".parse::<Template>().is_err());
+        assert!("%1024l".parse::<Template>().is_ok());
+        assert_eq!(
+            "%1025l".parse::<Template>(),
+            Err("widths cannot go over 1024".to_string())
+        );
+        assert!("%99999999999999999999l".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn test_enable_counts() {
+        let mut args =
+            Cli::parse_from(["wc", "--printf=%w %n", "--printf-total=%L total", "unused"]);
+        enable_counts(&mut args);
+
+        assert!(args.count_words && args.max_line_length);
+        assert!(!args.count_lines && !args.count_chars && !args.count_bytes);
+    }
+}
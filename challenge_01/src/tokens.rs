@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+type Merges = HashMap<(Vec<u8>, Vec<u8>), usize>;
+
+const CONTRACTIONS: [&str; 7] = ["'s", "'t", "'re", "'ve", "'m", "'ll", "'d"];
+
+// GPT-2 merges.txt files rank pairs of symbols written with its byte to
+// unicode table and merge every occurrence of the best pair at once.
+// tiktoken files rank whole base64 encoded tokens and merge the leftmost best
+// pair only.
+#[derive(Debug)]
+enum Ranks {
+    Merges(Merges),
+    Tokens(HashMap<Vec<u8>, usize>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Vocab(Arc<Ranks>);
+
+impl Vocab {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|_| "Error at reading the vocabulary".to_string())?;
+        Vocab::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let first = text
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default();
+        let tiktoken = match first.split_once(' ') {
+            Some((token, rank)) => rank.parse::<usize>().is_ok() && decode_base64(token).is_some(),
+            None => false,
+        };
+
+        let ranks = if tiktoken {
+            Ranks::Tokens(parse_tiktoken(text)?)
+        } else {
+            Ranks::Merges(parse_merges(text)?)
+        };
+        Ok(Vocab(Arc::new(ranks)))
+    }
+
+    fn rank(&self, left: &[u8], right: &[u8]) -> Option<usize> {
+        match &*self.0 {
+            Ranks::Merges(merges) => merges.get(&(left.to_vec(), right.to_vec())).copied(),
+            Ranks::Tokens(tokens) => tokens.get(&[left, right].concat()).copied(),
+        }
+    }
+
+    // Merges files come from GPT-2 and are split with its pattern, tiktoken
+    // files with cl100k's.
+    fn pieces<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        match &*self.0 {
+            Ranks::Merges(_) => pieces(text, gpt2_piece_len),
+            Ranks::Tokens(_) => pieces(text, cl100k_piece_len),
+        }
+    }
+
+    fn encode(&self, piece: &[u8]) -> Vec<Vec<u8>> {
+        let mut parts: Vec<Vec<u8>> = piece.iter().map(|&byte| vec![byte]).collect();
+
+        while let Some((_, index)) = parts
+            .windows(2)
+            .enumerate()
+            .filter_map(|(index, pair)| self.rank(&pair[0], &pair[1]).map(|rank| (rank, index)))
+            .min()
+        {
+            match &*self.0 {
+                Ranks::Merges(_) => {
+                    let pair = (parts[index].clone(), parts[index + 1].clone());
+                    parts = merge_all(parts, &pair);
+                }
+                Ranks::Tokens(_) => {
+                    let right = parts.remove(index + 1);
+                    parts[index].extend(right);
+                }
+            }
+        }
+
+        parts
+    }
+}
+
+fn merge_all(parts: Vec<Vec<u8>>, pair: &(Vec<u8>, Vec<u8>)) -> Vec<Vec<u8>> {
+    let mut merged = Vec::with_capacity(parts.len());
+    let mut parts = parts.into_iter().peekable();
+    while let Some(part) = parts.next() {
+        if part == pair.0 && parts.peek() == Some(&pair.1) {
+            parts.next();
+            merged.push([&pair.0[..], &pair.1[..]].concat());
+        } else {
+            merged.push(part);
+        }
+    }
+
+    merged
+}
+
+fn parse_merges(text: &str) -> Result<Merges, String> {
+    let decoder: HashMap<char, u8> = byte_chars()
+        .into_iter()
+        .enumerate()
+        .map(|(byte, c)| (c, byte as u8))
+        .collect();
+    let symbol = |symbol: &str| -> Option<Vec<u8>> {
+        symbol.chars().map(|c| decoder.get(&c).copied()).collect()
+    };
+
+    let mut merges = HashMap::new();
+    let lines = text
+        .lines()
+        .filter(|line| !line.starts_with("#version") && !line.trim().is_empty());
+    for (rank, line) in lines.enumerate() {
+        let pair = line
+            .split_once(' ')
+            .and_then(|(left, right)| Some((symbol(left)?, symbol(right)?)))
+            .filter(|(left, right)| !left.is_empty() && !right.is_empty())
+            .ok_or_else(|| format!("invalid merge \"{}\"", line))?;
+        merges.entry(pair).or_insert(rank);
+    }
+
+    Ok(merges)
+}
+
+fn parse_tiktoken(text: &str) -> Result<HashMap<Vec<u8>, usize>, String> {
+    let mut tokens = HashMap::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let (token, rank) = line
+            .split_once(' ')
+            .and_then(|(token, rank)| Some((decode_base64(token)?, rank.trim().parse().ok()?)))
+            .filter(|(token, _)| !token.is_empty())
+            .ok_or_else(|| format!("invalid token \"{}\"", line))?;
+        tokens.insert(token, rank);
+    }
+
+    Ok(tokens)
+}
+
+// GPT-2 writes printable Latin-1 bytes as themselves and shifts the others to
+// U+0100 and up, in byte order.
+fn byte_chars() -> [char; 256] {
+    let mut chars = ['\0'; 256];
+    let mut shifted = 256;
+    for byte in 0..=255u8 {
+        chars[byte as usize] = match byte {
+            b'!'..=b'~' | 0xa1..=0xac | 0xae..=0xff => char::from(byte),
+            _ => {
+                shifted += 1;
+                char::from_u32(shifted - 1).unwrap()
+            }
+        };
+    }
+
+    chars
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    if text.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6 | value as u32) & 0xfff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+fn is_letter(c: char) -> bool {
+    matches!(
+        get_general_category(c),
+        GeneralCategory::UppercaseLetter
+            | GeneralCategory::LowercaseLetter
+            | GeneralCategory::TitlecaseLetter
+            | GeneralCategory::ModifierLetter
+            | GeneralCategory::OtherLetter
+    )
+}
+
+fn is_number(c: char) -> bool {
+    matches!(
+        get_general_category(c),
+        GeneralCategory::DecimalNumber
+            | GeneralCategory::LetterNumber
+            | GeneralCategory::OtherNumber
+    )
+}
+
+fn is_other(c: char) -> bool {
+    !c.is_whitespace() && !is_letter(c) && !is_number(c)
+}
+
+fn run(text: &str, class: fn(char) -> bool) -> usize {
+    text.char_indices()
+        .find(|&(_, c)| !class(c))
+        .map_or(text.len(), |(index, _)| index)
+}
+
+fn pieces(text: &str, piece_len: fn(&str) -> usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let len = piece_len(rest);
+        pieces.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    pieces
+}
+
+// GPT-2's pattern
+// 's|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+
+fn gpt2_piece_len(text: &str) -> usize {
+    if let Some(contraction) = CONTRACTIONS.iter().find(|&&c| text.starts_with(c)) {
+        return contraction.len();
+    }
+
+    let body = text.strip_prefix(' ').unwrap_or(text);
+    let class: Option<fn(char) -> bool> = match body.chars().next() {
+        Some(c) if is_letter(c) => Some(is_letter),
+        Some(c) if is_number(c) => Some(is_number),
+        Some(c) if is_other(c) => Some(is_other),
+        _ => None,
+    };
+    if let Some(class) = class {
+        return text.len() - body.len() + run(body, class);
+    }
+
+    spaces_len(text)
+}
+
+// cl100k's pattern
+// (?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*
+// |\s*[\r\n]+|\s+(?!\S)|\s+
+fn cl100k_piece_len(text: &str) -> usize {
+    let contraction = CONTRACTIONS.iter().find(|c| {
+        text.get(..c.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(c))
+    });
+    if let Some(contraction) = contraction {
+        return contraction.len();
+    }
+
+    let first = text.chars().next().unwrap_or_default();
+    let rest = &text[first.len_utf8()..];
+    if is_letter(first)
+        || (!matches!(first, '\r' | '\n') && !is_number(first) && rest.starts_with(is_letter))
+    {
+        return first.len_utf8() + run(rest, is_letter);
+    }
+    if is_number(first) {
+        return text
+            .chars()
+            .take_while(|&c| is_number(c))
+            .take(3)
+            .map(char::len_utf8)
+            .sum();
+    }
+
+    let body = text.strip_prefix(' ').unwrap_or(text);
+    if body.starts_with(is_other) {
+        let end = text.len() - body.len() + run(body, is_other);
+        return end + run(&text[end..], |c| matches!(c, '\r' | '\n'));
+    }
+
+    // Spaces up to a line break stay with it.
+    let spaces = run(text, char::is_whitespace);
+    match text[..spaces].rfind(['\r', '\n']) {
+        Some(newline) => newline + 1,
+        None => spaces_len(text),
+    }
+}
+
+// A run of spaces leaves its last one to the word that follows.
+fn spaces_len(text: &str) -> usize {
+    let spaces = run(text, char::is_whitespace);
+    match text[..spaces].chars().next_back() {
+        Some(last) if spaces < text.len() && spaces > last.len_utf8() => spaces - last.len_utf8(),
+        _ => spaces,
+    }
+}
+
+// Text arrives a record at a time. Every piece but the last is final, the last
+// one may still grow and waits for the next record or the end of the file.
+pub struct TokenCounter<'a> {
+    vocab: &'a Vocab,
+    pending: String,
+    tokens: usize,
+    cache: HashMap<String, usize>,
+}
+
+impl<'a> TokenCounter<'a> {
+    pub fn new(vocab: &'a Vocab) -> Self {
+        TokenCounter {
+            vocab,
+            pending: String::new(),
+            tokens: 0,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, text: &str) {
+        self.pending.push_str(text);
+        let pieces = self.vocab.pieces(&self.pending);
+        let Some((last, complete)) = pieces.split_last() else {
+            return;
+        };
+        for piece in complete {
+            self.tokens += count_piece(self.vocab, &mut self.cache, piece);
+        }
+        self.pending = last.to_string();
+    }
+
+    pub fn finish(mut self) -> usize {
+        for piece in self.vocab.pieces(&self.pending) {
+            self.tokens += count_piece(self.vocab, &mut self.cache, piece);
+        }
+
+        self.tokens
+    }
+}
+
+fn count_piece(vocab: &Vocab, cache: &mut HashMap<String, usize>, piece: &str) -> usize {
+    if let Some(&tokens) = cache.get(piece) {
+        return tokens;
+    }
+    let tokens = vocab.encode(piece.as_bytes()).len();
+    cache.insert(piece.to_string(), tokens);
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MERGES: &str = "#version: 0.2\nĠ t\nh e\nĠt he\nĠ c\na t\nĠc at\n";
+
+    #[test]
+    fn test_pieces() {
+        assert_eq!(
+            pieces("Hello world's  3x\n\nok  ", gpt2_piece_len),
+            ["Hello", " world", "'s", " ", " 3", "x", "\n", "\n", "ok", "  "]
+        );
+        assert_eq!(
+            pieces("café, naïve!?", gpt2_piece_len),
+            ["café", ",", " naïve", "!?"]
+        );
+        assert_eq!(
+            pieces("I'M 12345 x:y!\n\n", gpt2_piece_len),
+            ["I", "'", "M", " 12345", " x", ":", "y", "!", "\n\n"]
+        );
+    }
+
+    #[test]
+    fn test_cl100k_pieces() {
+        assert_eq!(
+            pieces("Hello world's  3x\n\nok  ", cl100k_piece_len),
+            ["Hello", " world", "'s", " ", " ", "3", "x", "\n\n", "ok", "  "]
+        );
+        assert_eq!(
+            pieces("I'M 12345 x:y!\n\n", cl100k_piece_len),
+            ["I", "'M", " ", "123", "45", " x", ":y", "!\n\n"]
+        );
+        assert_eq!(
+            pieces("a  \n  b\tc", cl100k_piece_len),
+            ["a", "  \n", " ", " b", "\tc"]
+        );
+    }
+
+    #[test]
+    fn test_merges_vocab() {
+        let vocab = Vocab::parse(MERGES).expect("invalid merges");
+
+        assert_eq!(vocab.encode(b"the"), [b"t".to_vec(), b"he".to_vec()]);
+        assert_eq!(vocab.encode(b" cat"), [b" cat".to_vec()]);
+        assert_eq!(
+            vocab.encode(b" tatat"),
+            [b" t".to_vec(), b"at".to_vec(), b"at".to_vec()]
+        );
+        assert!(Vocab::parse("#version: 0.2\nab\n").is_err());
+    }
+
+    #[test]
+    fn test_tiktoken_vocab() {
+        let vocab =
+            Vocab::parse("dA== 0\naA== 1\nZQ== 2\naGU= 3\ndGhl 4\n").expect("invalid ranks");
+
+        assert_eq!(vocab.encode(b"the"), [b"the".to_vec()]);
+        assert_eq!(vocab.encode(b"het"), [b"he".to_vec(), b"t".to_vec()]);
+        assert_eq!(decode_base64("IGNhdA=="), Some(b" cat".to_vec()));
+        assert!(Vocab::parse("dA== 0\nnot base64!\n").is_err());
+    }
+
+    // Excerpts of the published GPT-2 merges.txt and cl100k_base.tiktoken:
+    // the merges and tokens on the way to the tokens of these texts.
+    fn excerpt(name: &str) -> Vocab {
+        let text = std::fs::read_to_string(format!("input_files/{}", name))
+            .expect("failed to read fixture");
+        Vocab::parse(&text).expect("invalid vocabulary")
+    }
+
+    fn tokens(vocab: &Vocab, text: &str) -> Vec<String> {
+        vocab
+            .pieces(text)
+            .into_iter()
+            .flat_map(|piece| vocab.encode(piece.as_bytes()))
+            .map(|token| String::from_utf8(token).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_published_tokens() {
+        let gpt2 = excerpt("gpt2_merges_excerpt.txt");
+        assert_eq!(tokens(&gpt2, " hello world"), [" hello", " world"]);
+        assert_eq!(tokens(&gpt2, "hello\n\n"), ["hello", "\n\n"]);
+        assert_eq!(tokens(&gpt2, " 12345").len(), 6);
+
+        let cl100k = excerpt("cl100k_excerpt.tiktoken");
+        let Ranks::Tokens(ranks) = &*cl100k.0 else {
+            panic!("not a tiktoken vocabulary");
+        };
+        let ids = |text: &str| -> Vec<usize> {
+            tokens(&cl100k, text)
+                .iter()
+                .map(|token| ranks[token.as_bytes()])
+                .collect()
+        };
+        assert_eq!(ids("hello world"), [15339, 1917]);
+        assert_eq!(ids("12345"), [4513, 1774]);
+        assert_eq!(ids("\n\n"), [271]);
+    }
+
+    #[test]
+    fn test_token_counter_across_records() {
+        let vocab = Vocab::parse(MERGES).expect("invalid merges");
+        let mut counter = TokenCounter::new(&vocab);
+        for record in ["the cat", "", "sat"] {
+            counter.push(record);
+            counter.push("\n");
+        }
+
+        // t he | " cat" | \n | \n | s at | \n
+        assert_eq!(counter.finish(), 8);
+    }
+}
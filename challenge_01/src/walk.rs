@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn collect_paths(paths: &[PathBuf], recursive: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if recursive && path.is_dir() {
+            walk(path, &mut files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    files
+}
+
+// Symlinked directories are not followed, so links cannot create cycles.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            eprintln!("{}: Error at reading the directory", dir.display());
+            return;
+        }
+    };
+
+    let mut entries: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    for entry in entries {
+        match fs::symlink_metadata(&entry) {
+            Ok(metadata) if metadata.is_dir() => walk(&entry, files),
+            Ok(_) => files.push(entry),
+            Err(_) => eprintln!("{}: Error at reading the file", entry.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_paths_recursive() {
+        let root = std::env::temp_dir().join(format!("wc_walk_{}", std::process::id()));
+        fs::create_dir_all(root.join("b/c")).expect("failed to create test dirs");
+        for file in ["b/c/z.txt", "b/y.txt", "a.txt"] {
+            fs::write(root.join(file), "test").expect("failed to create test file");
+        }
+
+        let recursive = collect_paths(std::slice::from_ref(&root), true);
+        let flat = collect_paths(std::slice::from_ref(&root), false);
+        fs::remove_dir_all(&root).expect("error when removing test dirs");
+
+        assert_eq!(
+            recursive,
+            vec![
+                root.join("a.txt"),
+                root.join("b/c/z.txt"),
+                root.join("b/y.txt")
+            ]
+        );
+        assert_eq!(flat, vec![root]);
+    }
+}
@@ -1,72 +1,200 @@
-use clap::Parser;
+use crate::check::relative;
+use crate::classes::CharClasses;
+use crate::cli::Cli;
+use crate::decompress::Decompress;
+use crate::estimate::Estimate;
+use crate::git::read_blob;
+use crate::lang::{Language, Trigrams};
+use crate::numbers::NumberStyle;
+use crate::progress::{Progress, ProgressReader};
+use crate::prose::{Markup, Prose};
+use crate::range::{open_range, Span};
+use crate::special::check_input;
+use crate::split::{decode, terminated_records};
+use crate::tokens::TokenCounter;
+use serde::Serialize;
 use std::fs::File;
-use std::io::BufRead;
 use std::io::BufReader;
+use std::io::{BufRead, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+#[derive(Serialize)]
 pub struct ResultWc {
-    lines: usize,
-    words: usize,
-    chars: usize,
-    bytes: usize,
-    file_name: String,
+    pub lines: usize,
+    pub words: usize,
+    pub chars: usize,
+    pub bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_line_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<Language>,
+    #[serde(rename = "name")]
+    pub file_name: String,
+    // The path as given or found, for the modes that match on it.
+    #[serde(skip)]
+    pub path: PathBuf,
+    // Every byte read, line terminators included, whatever the flags.
+    #[serde(skip)]
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<Estimate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classes: Option<CharClasses>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<Span>,
 }
 
-#[derive(Parser)]
-pub struct Cli {
-    #[clap(short = 'l')]
-    pub count_lines: bool,
+fn open_file(path: &Path) -> Result<BufReader<File>, String> {
+    let file = File::open(path).map_err(|_| "Error at opening the file".to_string())?;
 
-    #[clap(short = 'w')]
-    pub count_words: bool,
+    Ok(BufReader::new(file))
+}
 
-    #[clap(short = 'c')]
-    pub count_chars: bool,
+pub fn open_input(
+    path: &Path,
+    args: &Cli,
+    progress: Option<&Arc<Progress>>,
+) -> Result<Box<dyn BufRead>, String> {
+    let reader = match &args.rev {
+        Some(rev) => track(Cursor::new(read_blob(path, rev)?), progress),
+        None => {
+            check_input(path, args.max_bytes)?;
+            let reader = open_file(path)?;
+            match args.max_bytes {
+                Some(max_bytes) => track(reader.take(max_bytes), progress),
+                None => track(reader, progress),
+            }
+        }
+    };
+    if args.raw {
+        return Ok(reader);
+    }
 
-    #[clap(short = 'b')]
-    pub count_bytes: bool,
+    let raw_deflate = path.extension().is_some_and(|ext| ext == "deflate");
+    let decoder = Decompress::new(reader, raw_deflate)
+        .map_err(|_| "Error at reading the file".to_string())?;
 
-    #[clap(short = 'm')]
-    pub count_all: bool,
+    Ok(Box::new(BufReader::new(decoder)))
+}
 
-    pub path: std::path::PathBuf,
+pub fn track<R: BufRead + 'static>(
+    reader: R,
+    progress: Option<&Arc<Progress>>,
+) -> Box<dyn BufRead> {
+    match progress {
+        Some(progress) => Box::new(BufReader::new(ProgressReader::new(
+            reader,
+            progress.clone(),
+        ))),
+        None => Box::new(reader),
+    }
 }
 
-fn open_file(path: &str) -> Result<BufReader<File>, String> {
-    let file = File::open(path).map_err(|_| "Error at opening the file".to_string())?;
+// A single file shows by its name. With -r or several paths, names could
+// clash, so rows show the path as walked from the argument.
+pub fn display_name(path: &Path, args: &Cli) -> String {
+    if args.recursive || args.paths.len() > 1 {
+        relative(path).to_string_lossy().into_owned()
+    } else {
+        file_name(path)
+    }
+}
 
-    Ok(BufReader::new(file))
+pub fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map_or_else(
+            || path.to_string_lossy(),
+            |file_name| file_name.to_string_lossy(),
+        )
+        .into_owned()
 }
 
-pub fn get_statistics() -> ResultWc {
-    let args = Cli::parse();
-    let file_name = args
-        .path
-        .file_name()
-        .and_then(|file_name| file_name.to_str())
-        .map(|file_name| file_name.to_string())
-        .expect("failed to convert file name to string");
+pub fn get_statistics(
+    path: &Path,
+    args: &Cli,
+    progress: Option<&Arc<Progress>>,
+) -> Result<ResultWc, String> {
+    let prose = args
+        .prose
+        .as_ref()
+        .map(|format| Prose::new(Markup::for_path(path, format)));
 
-    let reader = open_file(&args.path.to_str().unwrap()).unwrap();
+    if let Some(mut window) = open_range(path, args, progress)? {
+        let mut result = count(&mut window, args, prose, display_name(path, args))?;
+        result.range = Some(window.span());
+        return Ok(result);
+    }
+
+    let reader = open_input(path, args, progress)?;
+    count(reader, args, prose, display_name(path, args))
+}
 
+fn count<R: BufRead>(
+    reader: R,
+    args: &Cli,
+    mut prose: Option<Prose>,
+    file_name: String,
+) -> Result<ResultWc, String> {
     let mut lines = 0;
     let mut words = 0;
     let mut chars = 0;
     let mut bytes = 0;
-
-    for line in reader.lines() {
-        match line {
+    let mut max_line_length = args.max_line_length.then_some(0);
+    let mut classes = args.classes.then(CharClasses::default);
+    let mut tokens = args.tokens.as_ref().map(TokenCounter::new);
+    let mut trigrams = args.lang.then(Trigrams::default);
+    let mut total_size = 0;
+    for line in terminated_records(reader, args.record_sep.as_ref()) {
+        // A file that cannot be read, or decompressed, is an error, text that
+        // is not valid in its encoding only ends the count.
+        let (line, end) = line.map_err(|_| "Error at reading the file".to_string())?;
+        let size = line.len();
+        total_size += size + end.len();
+        match decode(line, &args.encoding) {
             Ok(line_str) => {
+                let (line_str, size) = match &mut prose {
+                    Some(prose) => match prose.line(&line_str) {
+                        Some(text) => {
+                            let size = text.len();
+                            (text, size)
+                        }
+                        None => continue,
+                    },
+                    None => (line_str, size),
+                };
                 if args.count_lines || args.count_all {
                     lines += 1;
                 }
                 if args.count_words || args.count_all {
-                    words += line_str.split_whitespace().count();
+                    words += match &args.word_delim {
+                        Some(rule) => rule.count(&line_str),
+                        None => line_str.split_whitespace().count(),
+                    };
                 }
                 if args.count_chars || args.count_all {
                     chars += line_str.chars().count();
                 }
                 if args.count_bytes {
-                    bytes += line_str.len();
+                    bytes += size;
+                }
+                if let Some(max_line_length) = &mut max_line_length {
+                    *max_line_length = line_str.chars().count().max(*max_line_length);
+                }
+                if let Some(classes) = &mut classes {
+                    classes.add_line(&line_str);
+                }
+                if let Some(trigrams) = &mut trigrams {
+                    trigrams.add_line(&line_str);
+                }
+                // The tokenizer sees the record with the bytes that ended it.
+                if let Some(tokens) = &mut tokens {
+                    tokens.push(&line_str);
+                    tokens.push(&String::from_utf8_lossy(&end));
                 }
             }
             Err(_) => {
@@ -76,31 +204,120 @@ pub fn get_statistics() -> ResultWc {
         }
     }
 
-    ResultWc {
+    Ok(ResultWc {
         lines,
         words,
         chars,
         bytes,
+        max_line_length,
+        tokens: tokens.map(TokenCounter::finish),
+        lang: trigrams.map(|trigrams| trigrams.detect()),
         file_name,
-    }
+        path: PathBuf::new(),
+        size: total_size,
+        estimate: None,
+        code_lines: prose.map(|prose| prose.code_lines),
+        classes,
+        range: None,
+    })
 }
 
 impl ResultWc {
-    pub fn format(&self) -> String {
+    pub fn total() -> Self {
+        ResultWc {
+            lines: 0,
+            words: 0,
+            chars: 0,
+            bytes: 0,
+            max_line_length: None,
+            tokens: None,
+            lang: None,
+            file_name: "total".to_string(),
+            path: PathBuf::new(),
+            size: 0,
+            estimate: None,
+            code_lines: None,
+            classes: None,
+            range: None,
+        }
+    }
+
+    pub fn add(&mut self, other: &ResultWc) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.size += other.size;
+        if let Some(max_line_length) = other.max_line_length {
+            self.max_line_length = self.max_line_length.max(Some(max_line_length));
+        }
+        if let Some(tokens) = other.tokens {
+            self.tokens = Some(self.tokens.unwrap_or(0) + tokens);
+        }
+        if let Some(code_lines) = other.code_lines {
+            self.code_lines = Some(self.code_lines.unwrap_or(0) + code_lines);
+        }
+        if let Some(estimate) = &other.estimate {
+            self.estimate
+                .get_or_insert_with(Estimate::default)
+                .add(estimate);
+        }
+        if let Some(classes) = &other.classes {
+            self.classes
+                .get_or_insert_with(CharClasses::default)
+                .add(classes);
+        }
+    }
+
+    pub fn format(&self, style: &NumberStyle) -> String {
         let mut result = String::new();
-        if self.lines > 0 {
-            result.push_str(&format!("{} ", self.lines));
+        let margins = match &self.estimate {
+            Some(estimate) => [
+                estimate.lines,
+                estimate.words,
+                estimate.chars,
+                estimate.bytes,
+            ],
+            None => [0; 4],
+        };
+        let counts = [self.lines, self.words, self.chars, self.bytes];
+
+        for (index, (count, margin)) in counts.into_iter().zip(margins).enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let number = |value| match index {
+                3 => style.bytes(value),
+                _ => style.count(value),
+            };
+            match self.estimate {
+                Some(_) => result.push_str(&format!("~{}±{} ", number(count), number(margin))),
+                None => result.push_str(&format!("{} ", number(count))),
+            }
+        }
+        if let Some(max_line_length) = self.max_line_length {
+            result.push_str(&format!("{} ", style.count(max_line_length)));
         }
-        if self.words > 0 {
-            result.push_str(&format!("{} ", self.words));
+        if let Some(tokens) = self.tokens {
+            result.push_str(&format!("{} ", style.count(tokens)));
         }
-        if self.chars > 0 {
-            result.push_str(&format!("{} ", self.chars));
+        if let Some(lang) = &self.lang {
+            result.push_str(&format!("{} ", lang.format()));
         }
-        if self.bytes > 0 {
-            result.push_str(&format!("{} ", self.bytes));
+        result.push_str(&self.file_name);
+        if let Some(code_lines) = self.code_lines {
+            result.push_str(&format!(" ({} code lines excluded)", code_lines));
+        }
+        if let Some(range) = &self.range {
+            result.push_str(&range.format());
+        }
+        if let Some(estimate) = &self.estimate {
+            result.push_str(&format!(" (estimate, {} samples)", estimate.samples));
+        }
+        if let Some(classes) = &self.classes {
+            result.push('\n');
+            result.push_str(&classes.format());
         }
-        result.push_str(&format!("{}", self.file_name));
 
         result
     }
@@ -109,6 +326,8 @@ impl ResultWc {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use clap::Parser;
+    use flate2::write::GzEncoder;
     use std::fs::{self, File};
     use std::io::Write;
 
@@ -118,7 +337,7 @@ mod tests {
         let mut file = File::create(valid_test_file).expect("failed to create test file");
         writeln!(file, "test file").expect("failed to write to test file");
 
-        let result = open_file(valid_test_file);
+        let result = open_file(Path::new(valid_test_file));
         assert!(result.is_ok(), "expected ok, got err");
 
         fs::remove_file(valid_test_file).expect("error when removing test file");
@@ -128,9 +347,170 @@ mod tests {
     fn test_open_file_not_found() {
         let test_file = "invalid.txt";
 
-        let result = open_file(test_file);
+        let result = open_file(Path::new(test_file));
 
         assert!(result.is_err(), "expected err, got ok");
         assert_eq!(result.unwrap_err(), "Error at opening the file");
     }
+
+    #[test]
+    fn test_open_input_directory() {
+        let args = Cli::parse_from(["wc", "src"]);
+        let result = open_input(Path::new("src"), &args, None);
+
+        assert_eq!(result.err(), Some("Is a directory".to_string()));
+    }
+
+    #[test]
+    fn test_non_utf8_file_name() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::env::temp_dir().join(std::ffi::OsStr::from_bytes(b"wc_bad\xff.txt"));
+        fs::write(&path, "a b\n").expect("failed to create test file");
+        let args = Cli::parse_from(["wc", "-l", "-w", "unused"]);
+        let result = get_statistics(&path, &args, None);
+        fs::remove_file(&path).expect("error when removing test file");
+
+        assert_eq!(
+            result.unwrap().format(&NumberStyle::Plain),
+            "1 2 wc_bad\u{fffd}.txt"
+        );
+    }
+
+    #[test]
+    fn test_display_name() {
+        let path = Path::new("./src/cli/mod.rs");
+        let single = Cli::parse_from(["wc", "src/cli/mod.rs"]);
+        let recursive = Cli::parse_from(["wc", "-r", "."]);
+        let several = Cli::parse_from(["wc", "src/cli/mod.rs", "src/mod.rs"]);
+
+        assert_eq!(display_name(path, &single), "mod.rs");
+        assert_eq!(display_name(path, &recursive), "src/cli/mod.rs");
+        assert_eq!(display_name(path, &several), "src/cli/mod.rs");
+    }
+
+    #[test]
+    fn test_total() {
+        let args = Cli::parse_from(["wc", "-l", "-w", "unused"]);
+        let mut total = ResultWc::total();
+        total.add(&count("a b\nc\n".as_bytes(), &args, None, "one".to_string()).unwrap());
+        total.add(&count("d\n".as_bytes(), &args, None, "two".to_string()).unwrap());
+
+        assert_eq!(total.format(&NumberStyle::Plain), "3 4 total");
+    }
+
+    #[test]
+    fn test_format_with_number_style() {
+        let args = Cli::parse_from(["wc", "-m", "-b", "unused"]);
+        let text = "word ".repeat(300_000);
+        let result = count(text.as_bytes(), &args, None, "big".to_string()).unwrap();
+
+        assert_eq!(
+            result.format(&NumberStyle::Plain),
+            "1 300000 1500000 1500000 big"
+        );
+        assert_eq!(
+            result.format(&NumberStyle::Human(crate::cli::Units::Iec)),
+            "1 300K 1.5M 1.4MiB big"
+        );
+        assert_eq!(
+            result.format(&NumberStyle::Thousands(',')),
+            "1 300,000 1,500,000 1,500,000 big"
+        );
+    }
+
+    #[test]
+    fn test_max_line_length() {
+        let args = Cli::parse_from(["wc", "-l", "-L", "unused"]);
+        let mut total = ResultWc::total();
+        total.add(&count("ab\nabcd\n".as_bytes(), &args, None, "one".to_string()).unwrap());
+        total.add(&count("abc\n".as_bytes(), &args, None, "two".to_string()).unwrap());
+
+        assert_eq!(total.format(&NumberStyle::Plain), "3 4 total");
+    }
+
+    #[test]
+    fn test_count_custom_delimiters() {
+        let args = Cli::parse_from([
+            "wc",
+            "-l",
+            "-w",
+            "--record-sep=\\0",
+            "--word-delim=/",
+            "unused",
+        ]);
+        let result = count(
+            "./src/main.rs\0./my file.txt\0".as_bytes(),
+            &args,
+            None,
+            "find".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(result.format(&NumberStyle::Plain), "2 5 find");
+    }
+
+    #[test]
+    fn test_count_prose() {
+        let args = Cli::parse_from(["wc", "-l", "-w", "--prose", "unused"]);
+        let text = "# Usage\n\nRun [wc](https://example.com) now\n\n```\nwc -l file\n```\n";
+        let result = count(
+            text.as_bytes(),
+            &args,
+            Some(Prose::new(Markup::Markdown)),
+            "README.md".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.format(&NumberStyle::Plain),
+            "4 4 README.md (1 code lines excluded)"
+        );
+    }
+
+    #[test]
+    fn test_count_tokens_of_line_ends() {
+        let args = Cli::parse_from([
+            "wc",
+            "--tokens=input_files/cl100k_excerpt.tiktoken",
+            "unused",
+        ]);
+        let tokens = |text: &str| {
+            count(text.as_bytes(), &args, None, "text".to_string())
+                .unwrap()
+                .tokens
+        };
+
+        assert_eq!(tokens("hello world"), Some(2));
+        assert_eq!(tokens("hello world\n"), Some(3));
+        // The excerpt has no "\r\n" token, so the line end takes two.
+        assert_eq!(tokens("hello world\r\n"), Some(4));
+    }
+
+    #[test]
+    fn test_count_decompressed_gzip() {
+        let gzip_test_file = "test_file.txt.gz";
+        let mut encoder = GzEncoder::new(
+            File::create(gzip_test_file).expect("failed to create test file"),
+            flate2::Compression::default(),
+        );
+        writeln!(encoder, "one two\nthree").expect("failed to write to test file");
+        encoder.finish().expect("failed to finish gzip stream");
+
+        let args = Cli::parse_from(["wc", "-l", "-w", gzip_test_file]);
+        let raw_args = Cli::parse_from(["wc", "-l", "-w", "--raw", gzip_test_file]);
+        let decompressed = get_statistics(&args.paths[0], &args, None).unwrap();
+        let raw = get_statistics(&raw_args.paths[0], &raw_args, None).unwrap();
+
+        fs::remove_file(gzip_test_file).expect("error when removing test file");
+
+        assert_eq!(
+            decompressed.format(&NumberStyle::Plain),
+            "2 3 test_file.txt.gz"
+        );
+        assert_ne!(
+            raw.format(&NumberStyle::Plain),
+            decompressed.format(&NumberStyle::Plain)
+        );
+    }
 }
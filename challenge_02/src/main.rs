@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(PartialEq, Debug, Clone)]
 enum Token {
@@ -9,16 +10,46 @@ enum Token {
     Colon,
     Comma,
     String(String),
+    Number(Number),
+    True,
+    False,
+    Null,
 }
 
 #[derive(PartialEq, Debug)]
 enum ParseError {
     UnexpectedToken(String),
+    InvalidNumber(String),
+    NumberOutOfRange(String),
+}
+
+// Integers are kept exact when they fit in an i64, or a u64 above i64::MAX.
+// Larger ones become floats, like `-0` which only a float can hold. Floats
+// beyond f64's range are rejected, JSON has no way to write infinity.
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Number {
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::Int(value) => write!(f, "{}", value),
+            Number::UInt(value) => write!(f, "{}", value),
+            // Debug prints the shortest text that reads back as the same
+            // float and always keeps a fraction or an exponent.
+            Number::Float(value) => write!(f, "{:?}", value),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 enum JsonValue {
     String(String),
+    Number(Number),
+    Bool(bool),
     Object(HashMap<String, JsonValue>),
     Array(Vec<JsonValue>),
     Null,
@@ -48,6 +79,38 @@ fn tokenize(json: &str) -> Result<Vec<Token>, Vec<ParseError>> {
                 }
                 tokens.push(Token::String(key));
             }
+            '-' | '0'..='9' => {
+                let mut lexeme = c.to_string();
+                while let Some(&next_char) = chars.peek() {
+                    if !next_char.is_ascii_alphanumeric() && !matches!(next_char, '+' | '-' | '.') {
+                        break;
+                    }
+                    lexeme.push(next_char);
+                    chars.next();
+                }
+
+                match parse_number(&lexeme) {
+                    Ok(number) => tokens.push(Token::Number(number)),
+                    Err(err) => errors.push(err),
+                }
+            }
+            'a'..='z' | 'A'..='Z' => {
+                let mut keyword = c.to_string();
+                while let Some(&next_char) = chars.peek() {
+                    if !next_char.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    keyword.push(next_char);
+                    chars.next();
+                }
+
+                match keyword.as_str() {
+                    "true" => tokens.push(Token::True),
+                    "false" => tokens.push(Token::False),
+                    "null" => tokens.push(Token::Null),
+                    _ => errors.push(ParseError::UnexpectedToken(keyword)),
+                }
+            }
             ' ' | '\n' | '\t' => {}
             _ => {
                 let mut unexpected_token = String::new();
@@ -73,6 +136,70 @@ fn tokenize(json: &str) -> Result<Vec<Token>, Vec<ParseError>> {
     Ok(tokens)
 }
 
+// RFC 8259: -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?
+fn parse_number(lexeme: &str) -> Result<Number, ParseError> {
+    let invalid = || ParseError::InvalidNumber(lexeme.to_string());
+    let bytes = lexeme.as_bytes();
+    let digits = |mut index: usize| {
+        while bytes.get(index).is_some_and(u8::is_ascii_digit) {
+            index += 1;
+        }
+        index
+    };
+
+    let negative = bytes.first() == Some(&b'-');
+    let mut index = negative as usize;
+    index = match bytes.get(index) {
+        Some(b'0') => index + 1,
+        Some(b'1'..=b'9') => digits(index),
+        _ => return Err(invalid()),
+    };
+
+    let mut float = false;
+    if bytes.get(index) == Some(&b'.') {
+        let end = digits(index + 1);
+        if end == index + 1 {
+            return Err(invalid());
+        }
+        index = end;
+        float = true;
+    }
+    if matches!(bytes.get(index), Some(b'e' | b'E')) {
+        index += 1;
+        if matches!(bytes.get(index), Some(b'+' | b'-')) {
+            index += 1;
+        }
+        let end = digits(index);
+        if end == index {
+            return Err(invalid());
+        }
+        index = end;
+        float = true;
+    }
+    if index != bytes.len() {
+        return Err(invalid());
+    }
+
+    if !float {
+        match lexeme.parse::<i64>() {
+            Ok(0) if negative => return Ok(Number::Float(-0.0)),
+            Ok(value) => return Ok(Number::Int(value)),
+            Err(_) => {
+                if let Ok(value) = lexeme.parse::<u64>() {
+                    return Ok(Number::UInt(value));
+                }
+            }
+        }
+    }
+
+    let value: f64 = lexeme.parse().map_err(|_| invalid())?;
+    if value.is_infinite() {
+        return Err(ParseError::NumberOutOfRange(lexeme.to_string()));
+    }
+
+    Ok(Number::Float(value))
+}
+
 struct Parser {
     tokens: Vec<Token>,
     current: usize,
@@ -122,6 +249,18 @@ impl Parser {
                     self.advance();
                     Ok(JsonValue::String(value.clone()))
                 }
+                Token::Number(number) => {
+                    self.advance();
+                    Ok(JsonValue::Number(number))
+                }
+                Token::True | Token::False => {
+                    self.advance();
+                    Ok(JsonValue::Bool(token == Token::True))
+                }
+                Token::Null => {
+                    self.advance();
+                    Ok(JsonValue::Null)
+                }
                 Token::CurlyOpen => self.parse_object(),
                 Token::CurlyClose => {
                     self.advance();
@@ -267,6 +406,135 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn tokenize_should_tokenize_numbers() {
+        let result = match tokenize("{\"a\": 0, \"b\": -12.5e-3,\"c\": 18446744073709551615}") {
+            Ok(result) => result,
+            Err(_err) => panic!("error at test"),
+        };
+
+        assert_eq!(result[3], Token::Number(Number::Int(0)));
+        assert_eq!(result[7], Token::Number(Number::Float(-0.0125)));
+        assert_eq!(result[11], Token::Number(Number::UInt(u64::MAX)));
+        assert_eq!(result[12], Token::CurlyClose);
+    }
+
+    #[test]
+    fn tokenize_should_reject_invalid_numbers() {
+        for lexeme in ["01", "-", "1.", "1.e3", "2e", "-.5", "1.5x", "1e+-2"] {
+            assert_eq!(
+                tokenize(&format!("{{\"a\": {}}}", lexeme)),
+                Err(vec![ParseError::InvalidNumber(lexeme.to_string())])
+            );
+        }
+        assert_eq!(
+            tokenize("{\"a\": 1e400}"),
+            Err(vec![ParseError::NumberOutOfRange("1e400".to_string())])
+        );
+    }
+
+    #[test]
+    fn number_should_round_trip() {
+        let cases = [
+            ("0.1", Number::Float(0.1), "0.1"),
+            ("-0", Number::Float(-0.0), "-0.0"),
+            ("-0.0", Number::Float(-0.0), "-0.0"),
+            ("1E2", Number::Float(100.0), "100.0"),
+            ("1e-400", Number::Float(0.0), "0.0"),
+            (
+                "-9223372036854775808",
+                Number::Int(i64::MIN),
+                "-9223372036854775808",
+            ),
+            (
+                "9223372036854775808",
+                Number::UInt(1 << 63),
+                "9223372036854775808",
+            ),
+            (
+                "18446744073709551616",
+                Number::Float(1.8446744073709552e19),
+                "1.8446744073709552e19",
+            ),
+        ];
+
+        for (lexeme, number, text) in cases {
+            let parsed = parse_number(lexeme).expect("test number rejected");
+            assert_eq!(parsed, number);
+            assert_eq!(parsed.to_string(), text);
+            assert_eq!(parse_number(text), Ok(number));
+        }
+        assert!(parse_number("-0").is_ok_and(|number| match number {
+            Number::Float(value) => value.is_sign_negative(),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn parser_should_parse_number() {
+        let tokens = tokenize("{\"key\": -42}").expect("test tokenize number gone wrong");
+        let mut parser = Parser::new(tokens);
+        let mut map = HashMap::new();
+        map.insert("key".to_string(), JsonValue::Number(Number::Int(-42)));
+
+        let result = parser.parse();
+        let expected = JsonValue::Object(map);
+
+        assert_eq!(expected, result.expect("test parse number gone wrong"));
+    }
+
+    #[test]
+    fn tokenize_should_tokenize_keywords() {
+        let mut expected: Vec<Token> = vec![Token::CurlyOpen];
+        for (key, value) in [("a", Token::True), ("b", Token::False), ("c", Token::Null)] {
+            expected.push(Token::String(key.to_string()));
+            expected.push(Token::Colon);
+            expected.push(value);
+            expected.push(Token::Comma);
+        }
+        expected.pop();
+        expected.push(Token::CurlyClose);
+
+        let result = match tokenize("{\"a\": true, \"b\":false,\"c\": null}") {
+            Ok(result) => result,
+            Err(_err) => panic!("error at test"),
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn tokenize_should_reject_misspelled_keywords() {
+        for keyword in ["tru", "nulll", "False", "nul1", "truefalse"] {
+            assert_eq!(
+                tokenize(&format!("{{\"a\": {}}}", keyword)),
+                Err(vec![ParseError::UnexpectedToken(keyword.to_string())])
+            );
+        }
+    }
+
+    #[test]
+    fn parser_should_parse_keywords() {
+        let cases = [
+            ("true", JsonValue::Bool(true)),
+            ("false", JsonValue::Bool(false)),
+            ("null", JsonValue::Null),
+        ];
+
+        for (keyword, value) in cases {
+            let tokens = tokenize(&format!("{{\"key\": {}}}", keyword))
+                .expect("test tokenize keyword gone wrong");
+            let mut parser = Parser::new(tokens);
+            let mut map = HashMap::new();
+            map.insert("key".to_string(), value);
+
+            let result = parser.parse();
+            let expected = JsonValue::Object(map);
+
+            assert_eq!(expected, result.expect("test parse keyword gone wrong"));
+        }
+    }
+
     #[test]
     fn parser_should_parse_empty_string() {
         let mut tokens: Vec<Token> = Vec::new();